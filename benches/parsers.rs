@@ -0,0 +1,82 @@
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+use haveibeenpwnd::catalogue::Catalogue;
+use haveibeenpwnd::model::{breach_names_from_str, breaches_from_str, join_breaches, BreachName};
+
+use serde_json::{json, Value};
+
+use std::collections::BTreeMap;
+use std::hint::black_box;
+
+const CATALOGUE_SIZE: usize = 900;
+
+const DATA_CLASSES: &[&str] = &["Email addresses", "Passwords", "Usernames", "IP addresses",
+                                "Names", "Phone numbers", "Dates of birth", "Password hints"];
+
+fn catalogue_body() -> String {
+    let breaches: Vec<Value> = (0..CATALOGUE_SIZE)
+        .map(|i| {
+            let classes: Vec<_> = DATA_CLASSES.iter().cycle().skip(i).take(1 + i % 5).collect();
+            json!({
+                "Name": format!("Breach{}", i),
+                "Title": format!("Breach {}", i),
+                "Domain": format!("breach{}.example.com", i % 300),
+                "BreachDate": "2013-10-04",
+                "AddedDate": "2013-12-04T00:00:00Z",
+                "ModifiedDate": "2013-12-04T00:00:00Z",
+                "PwnCount": 1000 * i as u64,
+                "Description": "In October 2013, accounts were breached and \
+                                <a href=\"https://example.com\">published</a>.",
+                "DataClasses": classes,
+                "IsVerified": i % 7 != 0,
+                "IsFabricated": false,
+                "IsSensitive": i % 11 == 0,
+                "IsActive": true,
+                "IsRetired": false,
+                "IsSpamList": false,
+                "LogoType": "svg"
+            })
+        })
+        .collect();
+    serde_json::to_string(&breaches).unwrap()
+}
+
+fn truncated_body(count: usize) -> String {
+    let names: Vec<Value> = (0..count).map(|i| json!({ "Name": format!("Breach{}", i) })).collect();
+    serde_json::to_string(&names).unwrap()
+}
+
+fn parsing(c: &mut Criterion) {
+    let catalogue = catalogue_body();
+    let truncated = truncated_body(64);
+
+    let mut group = c.benchmark_group("parse");
+    group.throughput(Throughput::Bytes(catalogue.len() as u64));
+    group.bench_function("catalogue", |b| b.iter(|| breaches_from_str(black_box(&catalogue))));
+    group.throughput(Throughput::Bytes(truncated.len() as u64));
+    group.bench_function("truncated", |b| b.iter(|| breach_names_from_str(black_box(&truncated))));
+    group.finish();
+}
+
+fn lookup(c: &mut Criterion) {
+    let breaches = breaches_from_str(&catalogue_body()).unwrap();
+    let users: BTreeMap<String, Vec<BreachName>> = (0..1000)
+        .map(|i| {
+            let names = breach_names_from_str(&truncated_body(i % 20)).unwrap();
+            (format!("user{}@example.com", i), names)
+        })
+        .collect();
+
+    let catalogue = Catalogue::new(&breaches);
+
+    let mut group = c.benchmark_group("lookup");
+    group.bench_function("join", |b| b.iter(|| join_breaches(black_box(&users), &catalogue)));
+    group.bench_function("get", |b| {
+        b.iter(|| users.values().flatten().filter(|n| catalogue.get(n.as_ref()).is_some()).count())
+    });
+    group.bench_function("compact_catalogue", |b| b.iter(|| Catalogue::new(black_box(&breaches))));
+    group.finish();
+}
+
+criterion_group!(benches, parsing, lookup);
+criterion_main!(benches);
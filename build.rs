@@ -0,0 +1,49 @@
+use std::collections::HashSet;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+const VENDORED: &str = "data/dataclasses.json";
+
+fn variant_name(name: &str) -> String {
+    let mut variant: String = name.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            let first = chars.next().unwrap().to_ascii_uppercase();
+            first.to_string() + &chars.as_str().to_ascii_lowercase()
+        })
+        .collect();
+
+    if variant.starts_with(|c: char| c.is_ascii_digit()) {
+        variant.insert(0, 'N');
+    }
+    variant
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed={}", VENDORED);
+
+    let body = fs::read_to_string(VENDORED).expect("failed to read vendored data classes");
+    let names: Vec<String> = serde_json::from_str(&body).expect("data classes are not a JSON \
+                                                                 array of strings");
+
+    let mut seen = HashSet::new();
+    let mut out = String::from("data_classes! {\n");
+    for name in &names {
+        let variant = variant_name(name);
+        assert!(!variant.is_empty() && variant != "Other",
+                "data class {:?} has no usable variant name",
+                name);
+        assert!(seen.insert(variant.clone()),
+                "data class {:?} collides with another as {}",
+                name,
+                variant);
+        out.push_str(&format!("    {} => {:?},\n", variant, name));
+    }
+    out.push_str("}\n");
+
+    let dest = Path::new(&env::var("OUT_DIR").unwrap()).join("data_classes.rs");
+    fs::write(dest, out).expect("failed to write generated data classes");
+}
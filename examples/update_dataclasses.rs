@@ -0,0 +1,20 @@
+use haveibeenpwnd::clientv2::Clientv2;
+
+use serde_json::Value;
+
+use std::fs;
+
+fn main() {
+    let client = Clientv2::new("haveibeenpwnd-dataclass-updater");
+    let classes = client.get_data_classes().send().expect("failed to fetch data classes");
+
+    let mut names: Vec<&str> = classes.iter().map(|c| c.as_str()).collect();
+    names.sort_unstable();
+    names.dedup();
+
+    let body = Value::from(names.clone());
+    let body = serde_json::to_string_pretty(&body).unwrap() + "\n";
+    fs::write("data/dataclasses.json", body).expect("failed to write data/dataclasses.json");
+
+    println!("Wrote {} data classes to data/dataclasses.json", names.len());
+}
@@ -0,0 +1,17 @@
+#![no_main]
+
+use haveibeenpwnd::model::breach_names_from_str;
+use haveibeenpwnd::protocol::{self, Response};
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|body: &str| {
+    let _ = breach_names_from_str(body);
+
+    let response = Response {
+        status: 200,
+        body: body.to_owned(),
+        debug_headers: vec![],
+    };
+    let _ = protocol::parse_breach_names(&response);
+});
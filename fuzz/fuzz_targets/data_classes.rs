@@ -0,0 +1,9 @@
+#![no_main]
+
+use haveibeenpwnd::model::data_classes_from_str;
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|body: &str| {
+    let _ = data_classes_from_str(body);
+});
@@ -0,0 +1,24 @@
+#![no_main]
+
+use haveibeenpwnd::protocol::{self, AccountQuery, Endpoint, Request};
+use haveibeenpwnd::schema;
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|body: &str| {
+    let truncated = AccountQuery {
+        truncate: true,
+        ..AccountQuery::default()
+    };
+    let requests = [(Endpoint::BreachedAccount,
+                     protocol::breached_account_url("test@example.com", truncated)),
+                    (Endpoint::Breaches, protocol::breaches_url(None)),
+                    (Endpoint::Breach, protocol::breach_url("Adobe")),
+                    (Endpoint::DataClasses, protocol::data_classes_url()),
+                    (Endpoint::PasteAccount, protocol::paste_account_url("test@example.com"))];
+
+    for (endpoint, url) in requests {
+        let _ = schema::validate(&Request::get("fuzz", endpoint, url), body);
+    }
+    let _ = protocol::check_content(200, None, body);
+});
@@ -0,0 +1,55 @@
+#ifndef HAVEIBEENPWND_H
+#define HAVEIBEENPWND_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define HIBP_OK 0
+
+#define HIBP_INVALID_ARGUMENT -1
+
+#define HIBP_REQUEST_FAILED -2
+
+typedef struct HibpClient HibpClient;
+
+// Creates a client that identifies itself with `user_agent`.
+//
+// Returns null if `user_agent` is null or not UTF-8.
+//
+// # Safety
+//
+// `user_agent` must be null or point to a NUL-terminated string. The
+// returned client must be released with `hibp_client_free`.
+struct HibpClient *hibp_client_new(const char *user_agent);
+
+// Releases a client created by `hibp_client_new`.
+//
+// # Safety
+//
+// `client` must be null or a pointer returned by `hibp_client_new` that has
+// not already been freed.
+void hibp_client_free(struct HibpClient *client);
+
+// Looks up the breaches `account` appears in.
+//
+// On `HIBP_OK`, `*out` holds a JSON array of breach names. On
+// `HIBP_REQUEST_FAILED`, `*out` holds the error message. Either string must
+// be released with `hibp_string_free`.
+//
+// # Safety
+//
+// `client` must come from `hibp_client_new`, `account` must point to a
+// NUL-terminated string and `out` must be null or valid for writes.
+int hibp_check_account(const struct HibpClient *client, const char *account, char **out);
+
+// Releases a string returned through an `out` parameter.
+//
+// # Safety
+//
+// `s` must be null or a string handed out by this library that has not
+// already been freed.
+void hibp_string_free(char *s);
+
+#endif  /* HAVEIBEENPWND_H */
@@ -0,0 +1,551 @@
+use crate::catalogue::Catalogue;
+use crate::clientv2::Clientv2;
+use crate::errors::*;
+use crate::model::{Breach, BreachName};
+use crate::profile::Profile;
+use crate::protocol::Request;
+use crate::redact::Pseudonymizer;
+
+use serde_json::{Map, Value};
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+const DEFAULT_CHECKPOINT_EVERY: usize = 100;
+// Pseudonymized and stored next to the entries, so a file written under another key is caught
+// instead of silently re-checking every account.
+const KEY_CHECK: &str = "haveibeenpwnd-checkpoint";
+
+#[derive(Debug, Default)]
+pub struct AuditReport {
+    pub results: BTreeMap<String, Vec<BreachName>>,
+    pub failures: Vec<(String, Error)>,
+}
+
+#[derive(Debug, Default)]
+pub struct FetchedBreaches {
+    pub breaches: BTreeMap<BreachName, Breach>,
+    pub failures: Vec<(BreachName, Error)>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BatchJob {
+    pub accounts: usize,
+    pub paste_accounts: usize,
+    pub breach_lookups: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Estimate {
+    pub requests: u64,
+    pub duration: Option<Duration>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedRequest {
+    pub account: String,
+    pub url: String,
+    pub at: Option<Duration>,
+}
+
+impl fmt::Display for PlannedRequest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.at {
+            Some(at) => write!(f, "+{}s GET {}", at.as_secs(), self.url),
+            None => write!(f, "GET {}", self.url),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct DryRun {
+    pub planned: Vec<PlannedRequest>,
+    pub resumed: Vec<String>,
+    pub failures: Vec<(String, Error)>,
+}
+
+#[derive(Debug)]
+pub struct AuditRunner<'a> {
+    client: &'a Clientv2<'a>,
+    checkpoint: PathBuf,
+    workers: usize,
+    checkpoint_every: usize,
+}
+
+#[derive(Debug, Default)]
+struct Progress {
+    report: AuditReport,
+    // Keyed by pseudonym, as written to the checkpoint.
+    done: BTreeMap<String, Vec<BreachName>>,
+    queued: HashSet<String>,
+    since_checkpoint: usize,
+    checkpoint_error: Option<Error>,
+}
+
+// Reads a state file written by `write_keyed`, or None if it doesn't exist yet.
+pub(crate) fn read_keyed(path: &Path, pseudonymizer: &Pseudonymizer) -> Result<Option<Value>> {
+    let body = match fs::read_to_string(path) {
+        Ok(body) => body,
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(Error::Checkpoint(path.to_owned(), e)),
+    };
+
+    let data: Value = serde_json::from_str(&body)
+        .map_err(|e| Error::json("Failed to parse checkpoint", e))?;
+    if data.get("key").and_then(Value::as_str) != Some(&pseudonymizer.id(KEY_CHECK)) {
+        return Err(Error::PseudonymKeyMismatch(path.to_owned()));
+    }
+    Ok(Some(data))
+}
+
+pub(crate) fn write_keyed(path: &Path,
+                          pseudonymizer: &Pseudonymizer,
+                          mut body: Map<String, Value>)
+                          -> Result<()> {
+    body.insert("key".to_owned(), Value::String(pseudonymizer.id(KEY_CHECK)));
+    write_atomically(path, &Value::Object(body).to_string())
+}
+
+// Entries are keyed by `Pseudonymizer::id`, so the file never holds a plaintext address.
+pub fn load_checkpoint(path: &Path,
+                       pseudonymizer: &Pseudonymizer)
+                       -> Result<BTreeMap<String, Vec<BreachName>>> {
+    let Some(data) = read_keyed(path, pseudonymizer)? else {
+        return Ok(BTreeMap::new());
+    };
+    let done = data.get("done")
+        .and_then(Value::as_object)
+        .ok_or_else(|| Error::parse("Checkpoint has no \"done\" object"))?;
+
+    done.iter()
+        .map(|(account, names)| {
+            let names = names.as_array()
+                .ok_or_else(|| Error::parse(format!("Checkpoint entry for {:?} is not a list",
+                                                    account)))?
+                .iter()
+                .map(|n| {
+                    n.as_str()
+                        .ok_or_else(|| Error::parse("Checkpoint breach name is not a string"))
+                        .and_then(BreachName::new)
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok((account.clone(), names))
+        })
+        .collect()
+}
+
+pub fn write_checkpoint(path: &Path,
+                        pseudonymizer: &Pseudonymizer,
+                        done: &BTreeMap<String, Vec<BreachName>>)
+                        -> Result<()> {
+    let done = done.iter()
+        .map(|(account, names)| {
+            let names = names.iter().map(|n| Value::String(n.to_string())).collect();
+            (account.clone(), Value::Array(names))
+        })
+        .collect::<Map<_, _>>();
+
+    let mut body = Map::new();
+    body.insert("done".to_owned(), Value::Object(done));
+    write_keyed(path, pseudonymizer, body)
+}
+
+pub(crate) fn write_atomically(path: &Path, body: &str) -> Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+
+    fs::write(&tmp, body)
+        .and_then(|_| fs::rename(&tmp, path))
+        .map_err(|e| Error::Checkpoint(path.to_owned(), e))
+}
+
+pub fn fetch_breaches<'a, I>(client: &'a Clientv2<'a>,
+                             names: I,
+                             catalogue: &Catalogue)
+                             -> FetchedBreaches
+    where I: IntoIterator<Item = BreachName>
+{
+    let mut fetched = FetchedBreaches::default();
+    let mut attempted = HashSet::new();
+
+    // A failed name is reported and the rest still go ahead, so one outage doesn't throw away
+    // lookups that were already paid for.
+    for name in names {
+        if !attempted.insert(name.clone()) {
+            continue;
+        }
+
+        let breach = match catalogue.get(name.as_ref()) {
+            Some(breach) => Some(breach.to_breach()),
+            None => {
+                match client.get_breach(name.clone()).send() {
+                    Ok(breach) => breach,
+                    Err(e) => {
+                        fetched.failures.push((name, e));
+                        continue;
+                    }
+                }
+            }
+        };
+        if let Some(breach) = breach {
+            fetched.breaches.insert(name, breach);
+        }
+    }
+
+    fetched
+}
+
+// None means neither a rate limiter nor a quota limit paces requests, so timing only depends on
+// network latency.
+pub(crate) fn scheduled_at(client: &Clientv2, index: u64) -> Option<Duration> {
+    let times = |n: u64| u32::try_from(n).unwrap_or(u32::MAX);
+
+    let limited = client.rate_limiter().map(|l| l.interval().saturating_mul(times(index)));
+    let quota = client.quota();
+    let windows = quota.limit().map(|limit| {
+        let full_windows = index / u64::from(limit.max(1));
+        quota.window().saturating_mul(times(full_windows))
+    });
+    limited.max(windows)
+}
+
+pub fn estimate(client: &Clientv2, job: &BatchJob) -> Estimate {
+    let requests = (job.accounts + job.paste_accounts + job.breach_lookups) as u64;
+
+    Estimate {
+        requests,
+        duration: scheduled_at(client, requests.saturating_sub(1)),
+    }
+}
+
+pub(crate) fn plan_request(client: &Clientv2, account: &str, request: Request, index: usize)
+                           -> PlannedRequest {
+    PlannedRequest {
+        account: account.to_owned(),
+        url: request.redact_url(client.redaction_policy()),
+        at: scheduled_at(client, index as u64),
+    }
+}
+
+impl<'a> AuditRunner<'a> {
+    pub fn new<P: Into<PathBuf>>(client: &'a Clientv2<'a>, checkpoint: P) -> AuditRunner<'a> {
+        AuditRunner {
+            client,
+            checkpoint: checkpoint.into(),
+            workers: 1,
+            checkpoint_every: DEFAULT_CHECKPOINT_EVERY,
+        }
+    }
+
+    pub fn set_workers(&mut self, workers: usize) -> &mut Self {
+        self.workers = workers.max(1);
+        self
+    }
+
+    pub fn apply_profile(&mut self, profile: Profile) -> &mut Self {
+        self.set_workers(profile.workers())
+    }
+
+    pub fn set_checkpoint_every(&mut self, accounts: usize) -> &mut Self {
+        self.checkpoint_every = accounts.max(1);
+        self
+    }
+
+    pub fn checkpoint(&self) -> &Path {
+        &self.checkpoint
+    }
+
+    pub fn estimate(&self, accounts: &[String]) -> Result<Estimate> {
+        let done = load_checkpoint(&self.checkpoint, self.client.pseudonymizer())?;
+        let pending = accounts.iter()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .filter(|a| !done.contains_key(&self.client.pseudonym(a)))
+            .count();
+
+        let job = BatchJob {
+            accounts: pending,
+            ..BatchJob::default()
+        };
+        Ok(estimate(self.client, &job))
+    }
+
+    pub fn dry_run<I>(&self, accounts: I) -> Result<DryRun>
+        where I: IntoIterator<Item = String>
+    {
+        let done = load_checkpoint(&self.checkpoint, self.client.pseudonymizer())?;
+        let mut queued = HashSet::new();
+        let mut dry_run = DryRun::default();
+
+        for account in accounts {
+            if let Err(e) = self.client.validate_account(&account) {
+                dry_run.failures.push((account, e));
+            } else if done.contains_key(&self.client.pseudonym(&account)) {
+                dry_run.resumed.push(account);
+            } else if queued.insert(account.clone()) {
+                let request = self.client.get_breaches_acct(&account).truncated().request();
+                let planned = plan_request(self.client, &account, request, dry_run.planned.len());
+                dry_run.planned.push(planned);
+            }
+        }
+        Ok(dry_run)
+    }
+
+    fn check(&self, progress: &Mutex<Progress>, account: String) {
+        let result = self.client.get_breaches_acct(&account).truncated().send();
+        let mut progress = progress.lock().unwrap_or_else(|e| e.into_inner());
+
+        match result {
+            Ok(names) => {
+                progress.done.insert(self.client.pseudonym(&account), names.clone());
+                progress.report.results.insert(account, names);
+                progress.since_checkpoint += 1;
+            }
+            Err(e) => progress.report.failures.push((account, e)),
+        }
+
+        if progress.since_checkpoint >= self.checkpoint_every {
+            progress.since_checkpoint = 0;
+            let pseudonymizer = self.client.pseudonymizer();
+            if let Err(e) = write_checkpoint(&self.checkpoint, pseudonymizer, &progress.done) {
+                progress.checkpoint_error.get_or_insert(e);
+            }
+        }
+    }
+
+    pub fn run<I>(&self, accounts: I) -> Result<AuditReport>
+        where I: IntoIterator<Item = String>,
+              I::IntoIter: Send
+    {
+        let done = load_checkpoint(&self.checkpoint, self.client.pseudonymizer())?;
+        let pending = Mutex::new(accounts.into_iter());
+        let progress = Mutex::new(Progress {
+            done,
+            ..Progress::default()
+        });
+
+        thread::scope(|scope| {
+            for _ in 0..self.workers {
+                scope.spawn(|| {
+                    loop {
+                        let next = pending.lock().unwrap_or_else(|e| e.into_inner()).next();
+                        let Some(account) = next else { break };
+                        {
+                            let mut progress = progress.lock().unwrap_or_else(|e| e.into_inner());
+                            if progress.checkpoint_error.is_some() {
+                                break;
+                            }
+                            if !progress.queued.insert(account.clone()) {
+                                continue;
+                            }
+                            let pseudonym = self.client.pseudonym(&account);
+                            if let Some(names) = progress.done.get(&pseudonym).cloned() {
+                                progress.report.results.insert(account, names);
+                                continue;
+                            }
+                        }
+                        self.check(&progress, account);
+                    }
+                });
+            }
+        });
+
+        let progress = progress.into_inner().unwrap_or_else(|e| e.into_inner());
+        if let Some(e) = progress.checkpoint_error {
+            return Err(e);
+        }
+
+        write_checkpoint(&self.checkpoint, self.client.pseudonymizer(), &progress.done)?;
+        Ok(progress.report)
+    }
+}
+
+#[cfg(all(test, any(feature = "reqwest", feature = "ureq")))]
+mod tests {
+    use super::*;
+    use crate::backend::{BoxError, HttpBackend, HttpResponse};
+    use crate::ratelimit::RateLimiter;
+
+    use std::io::Cursor;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[derive(Debug, Default)]
+    struct Pwned(AtomicUsize);
+
+    impl HttpBackend for Pwned {
+        fn get(&self, request: &Request) -> std::result::Result<HttpResponse, BoxError> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            let (status, body) = if request.url.path().contains("pwned") {
+                (200, r#"[{"Name":"Adobe"}]"#)
+            } else if request.url.path().contains("outage") {
+                (503, "")
+            } else {
+                (404, "")
+            };
+
+            Ok(HttpResponse {
+                status,
+                content_type: Some("application/json".to_owned()),
+                retry_after: None,
+                content_length: None,
+                debug_headers: vec![],
+                body: Box::new(Cursor::new(body)),
+            })
+        }
+    }
+
+    fn accounts() -> Vec<String> {
+        ["pwned@example.com", "clean@example.com", "  "].iter().map(|a| a.to_string()).collect()
+    }
+
+    #[test]
+    fn resumes_from_checkpoint() {
+        let path = std::env::temp_dir().join(format!("hibp-audit-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let backend = Arc::new(Pwned::default());
+        let client = Clientv2::with_backend("test-rust-client", backend.clone());
+        let mut runner = AuditRunner::new(&client, &path);
+        runner.set_workers(2).set_checkpoint_every(1);
+
+        let mut repeated = accounts();
+        repeated.push("pwned@example.com".to_owned());
+        let report = runner.run(repeated).unwrap();
+        assert_eq!(report.results["pwned@example.com"], vec!["Adobe".parse().unwrap()]);
+        assert!(report.results["clean@example.com"].is_empty());
+        assert_eq!(report.failures.len(), 1);
+        assert_eq!(backend.0.load(Ordering::SeqCst), 2);
+
+        let report = runner.run(accounts()).unwrap();
+        assert_eq!(report.results.len(), 2);
+        assert_eq!(report.results["pwned@example.com"], vec!["Adobe".parse().unwrap()]);
+        assert_eq!(backend.0.load(Ordering::SeqCst), 2);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn checkpoints_hold_no_plaintext_accounts() {
+        let path = std::env::temp_dir().join(format!("hibp-keyed-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let mut client = Clientv2::with_backend("test-rust-client", Arc::new(Pwned::default()));
+        client.set_pseudonym_key(b"secret");
+        AuditRunner::new(&client, &path).run(accounts()).unwrap();
+
+        let body = fs::read_to_string(&path).unwrap();
+        assert!(!body.contains("example.com"));
+        assert!(load_checkpoint(&path, client.pseudonymizer()).unwrap()
+            .contains_key(&client.pseudonym("pwned@example.com")));
+
+        let other = Clientv2::with_backend("test-rust-client", Arc::new(Pwned::default()));
+        match AuditRunner::new(&other, &path).run(accounts()) {
+            Err(Error::PseudonymKeyMismatch(p)) => assert_eq!(p, path),
+            other => panic!("expected a key mismatch, got {:?}", other),
+        }
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn outages_are_not_recorded_as_clean() {
+        let path = std::env::temp_dir().join(format!("hibp-outage-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let client = Clientv2::with_backend("test-rust-client", Arc::new(Pwned::default()));
+        let report = AuditRunner::new(&client, &path).run(vec!["outage@example.com".to_owned()])
+            .unwrap();
+
+        assert!(report.results.is_empty());
+        assert_eq!(report.failures.len(), 1);
+        assert!(load_checkpoint(&path, client.pseudonymizer()).unwrap().is_empty());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn estimates_under_the_rate_limit() {
+        let mut client = Clientv2::with_backend("test-rust-client", Arc::new(Pwned::default()));
+        let job = BatchJob {
+            accounts: 10,
+            paste_accounts: 10,
+            ..BatchJob::default()
+        };
+        assert_eq!(estimate(&client, &job).duration, None);
+
+        client.set_rate_limiter(Arc::new(RateLimiter::new(Duration::from_millis(1500))))
+            .set_quota(Duration::from_secs(60), Some(5));
+        let estimate = estimate(&client, &job);
+        assert_eq!(estimate.requests, 20);
+        assert_eq!(estimate.duration, Some(Duration::from_secs(180)));
+    }
+
+    #[test]
+    fn dry_run_plans_without_sending() {
+        let path = std::env::temp_dir().join(format!("hibp-dry-run-{}.json", std::process::id()));
+        let backend = Arc::new(Pwned::default());
+        let mut client = Clientv2::with_backend("test-rust-client", backend.clone());
+        client.set_rate_limiter(Arc::new(RateLimiter::new(Duration::from_secs(2))));
+
+        let mut done = BTreeMap::new();
+        done.insert(client.pseudonym("done@example.com"), vec![]);
+        write_checkpoint(&path, client.pseudonymizer(), &done).unwrap();
+
+        let mut accounts = accounts();
+        accounts.extend(["done@example.com".to_owned(), "pwned@example.com".to_owned()]);
+        let dry_run = AuditRunner::new(&client, &path).dry_run(accounts).unwrap();
+
+        let planned: Vec<String> = dry_run.planned.iter().map(ToString::to_string).collect();
+        assert_eq!(planned,
+                   ["+0s GET https://haveibeenpwned.com/api/v2/breachedaccount/redacted\
+                     ?truncateResponse=true",
+                    "+2s GET https://haveibeenpwned.com/api/v2/breachedaccount/redacted\
+                     ?truncateResponse=true"]);
+        assert_eq!(dry_run.planned[1].account, "clean@example.com");
+        assert_eq!(dry_run.resumed, ["done@example.com"]);
+        assert_eq!(dry_run.failures.len(), 1);
+        assert_eq!(backend.0.load(Ordering::SeqCst), 0);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn fan_out_skips_catalogue_entries() {
+        let backend = Arc::new(Pwned::default());
+        let client = Clientv2::with_backend("test-rust-client", backend.clone());
+        let breaches = crate::model::breaches_from_str(r#"[{"Name":"Adobe"}]"#).unwrap();
+        let catalogue = Catalogue::new(&breaches);
+
+        let names = ["Adobe", "Adobe", "Unlisted"].iter().map(|n| n.parse().unwrap());
+        let fetched = fetch_breaches(&client, names, &catalogue);
+
+        assert_eq!(fetched.breaches.keys().map(|n| n.to_string()).collect::<Vec<_>>(),
+                   vec!["Adobe"]);
+        assert!(fetched.failures.is_empty());
+        assert_eq!(backend.0.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn fan_out_continues_past_failed_names() {
+        let backend = Arc::new(Pwned::default());
+        let client = Clientv2::with_backend("test-rust-client", backend.clone());
+        let breaches = crate::model::breaches_from_str(r#"[{"Name":"Adobe"}]"#).unwrap();
+        let catalogue = Catalogue::new(&breaches);
+
+        let names = ["outage", "Adobe", "outage", "Unlisted"].iter().map(|n| n.parse().unwrap());
+        let fetched = fetch_breaches(&client, names, &catalogue);
+
+        assert!(fetched.breaches.contains_key(&"Adobe".parse().unwrap()));
+        assert_eq!(fetched.failures.len(), 1);
+        assert_eq!(fetched.failures[0].0.to_string(), "outage");
+        assert_eq!(backend.0.load(Ordering::SeqCst), 2);
+    }
+}
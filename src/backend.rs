@@ -0,0 +1,132 @@
+use crate::protocol::Request;
+#[cfg(any(feature = "reqwest", feature = "ureq"))]
+use crate::protocol::DEBUG_HEADERS;
+
+use std::error::Error as StdError;
+use std::fmt::{self, Debug};
+use std::io::Read;
+
+pub type BoxError = Box<dyn StdError + Send + Sync>;
+
+pub struct HttpResponse {
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub retry_after: Option<String>,
+    pub content_length: Option<u64>,
+    pub debug_headers: Vec<(String, String)>,
+    pub body: Box<dyn Read>,
+}
+
+impl Debug for HttpResponse {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("HttpResponse")
+            .field("status", &self.status)
+            .field("content_type", &self.content_type)
+            .field("retry_after", &self.retry_after)
+            .field("content_length", &self.content_length)
+            .field("debug_headers", &self.debug_headers)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(any(feature = "reqwest", feature = "ureq"))]
+fn debug_headers<F>(header: F) -> Vec<(String, String)>
+    where F: Fn(&'static str) -> Option<String>
+{
+    DEBUG_HEADERS.iter().filter_map(|&name| header(name).map(|v| (name.to_owned(), v))).collect()
+}
+
+pub trait HttpBackend: Debug + Send + Sync {
+    fn get(&self, request: &Request) -> Result<HttpResponse, BoxError>;
+}
+
+#[cfg(feature = "reqwest")]
+pub use self::reqwest_backend::ReqwestBackend;
+
+#[cfg(feature = "reqwest")]
+mod reqwest_backend {
+    use super::*;
+
+    use reqwest::blocking::Client as HttpClient;
+    use reqwest::header::{HeaderName, CONTENT_TYPE, RETRY_AFTER};
+
+    #[derive(Debug, Clone, Default)]
+    pub struct ReqwestBackend(pub HttpClient);
+
+    impl HttpBackend for ReqwestBackend {
+        fn get(&self, request: &Request) -> Result<HttpResponse, BoxError> {
+            let mut builder = self.0.get(request.url.clone());
+            for (name, value) in &request.headers {
+                builder = builder.header(*name, value.as_str());
+            }
+
+            // reqwest errors carry the URL, which would leak the account through source().
+            let res = builder.send().map_err(|e| e.without_url())?;
+            let header = |name| {
+                res.headers().get(name).and_then(|v| v.to_str().ok()).map(String::from)
+            };
+
+            Ok(HttpResponse {
+                status: res.status().as_u16(),
+                content_type: header(CONTENT_TYPE),
+                retry_after: header(RETRY_AFTER),
+                content_length: res.content_length(),
+                debug_headers: debug_headers(|name| header(HeaderName::from_static(name))),
+                body: Box::new(res),
+            })
+        }
+    }
+}
+
+#[cfg(feature = "ureq")]
+pub use self::ureq_backend::UreqBackend;
+
+#[cfg(feature = "ureq")]
+mod ureq_backend {
+    use super::*;
+
+    use ureq::Agent;
+
+    #[derive(Debug, Clone)]
+    pub struct UreqBackend(pub Agent);
+
+    impl Default for UreqBackend {
+        fn default() -> UreqBackend {
+            let config = Agent::config_builder().http_status_as_error(false).build();
+            UreqBackend(config.into())
+        }
+    }
+
+    // These variants embed the request URI in their message, and with it the account.
+    fn without_url(e: ureq::Error) -> BoxError {
+        match e {
+            ureq::Error::BadUri(_) => "bad uri".into(),
+            ureq::Error::RequireHttpsOnly(_) => "configured for https only".into(),
+            e => Box::new(e),
+        }
+    }
+
+    impl HttpBackend for UreqBackend {
+        fn get(&self, request: &Request) -> Result<HttpResponse, BoxError> {
+            let mut builder = self.0.get(request.url.as_str());
+            for (name, value) in &request.headers {
+                builder = builder.header(*name, value.as_str());
+            }
+
+            let res = builder.call().map_err(without_url)?;
+            let header = |name| {
+                res.headers().get(name).and_then(|v| v.to_str().ok()).map(String::from)
+            };
+            let content_length = header("content-length").and_then(|len| len.parse().ok());
+
+            Ok(HttpResponse {
+                status: res.status().as_u16(),
+                content_type: header("content-type"),
+                retry_after: header("retry-after"),
+                content_length,
+                debug_headers: debug_headers(header),
+                body: Box::new(res.into_body().into_reader()),
+            })
+        }
+    }
+}
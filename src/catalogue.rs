@@ -0,0 +1,275 @@
+use crate::model::Breach;
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Default)]
+pub struct Interner {
+    strings: HashSet<Arc<str>>,
+}
+
+impl Interner {
+    pub fn new() -> Interner {
+        Interner::default()
+    }
+
+    pub fn intern(&mut self, s: &str) -> Arc<str> {
+        if let Some(interned) = self.strings.get(s) {
+            return interned.clone();
+        }
+
+        let interned: Arc<str> = Arc::from(s);
+        self.strings.insert(interned.clone());
+        interned
+    }
+
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompactBreach {
+    pub name: Box<str>,
+    pub title: Option<Box<str>>,
+    pub domain: Option<Arc<str>>,
+    pub breach_date: Option<Box<str>>,
+    pub added_date: Option<Box<str>>,
+    pub pwn_count: Option<u64>,
+    pub description: Option<Box<str>>,
+    pub data_classes: Option<Box<[Arc<str>]>>,
+    pub is_verified: Option<bool>,
+    pub is_sensitive: Option<bool>,
+    pub is_retired: Option<bool>,
+}
+
+impl CompactBreach {
+    pub fn new(breach: &Breach, interner: &mut Interner) -> CompactBreach {
+        let boxed = |s: &Option<String>| s.as_deref().map(Box::from);
+
+        CompactBreach {
+            name: Box::from(breach.name.as_str()),
+            title: boxed(&breach.title),
+            domain: breach.domain.as_deref().map(|d| interner.intern(d)),
+            breach_date: boxed(&breach.breach_date),
+            added_date: boxed(&breach.added_date),
+            pwn_count: breach.pwn_count,
+            description: boxed(&breach.description),
+            data_classes: breach.data_classes
+                .as_ref()
+                .map(|classes| classes.iter().map(|c| interner.intern(c)).collect()),
+            is_verified: breach.is_verified,
+            is_sensitive: breach.is_sensitive,
+            is_retired: breach.is_retired,
+        }
+    }
+
+    pub fn to_breach(&self) -> Breach {
+        let owned = |s: &Option<Box<str>>| s.as_deref().map(String::from);
+
+        Breach {
+            name: self.name.to_string(),
+            title: owned(&self.title),
+            domain: self.domain.as_deref().map(String::from),
+            breach_date: owned(&self.breach_date),
+            added_date: owned(&self.added_date),
+            pwn_count: self.pwn_count,
+            description: owned(&self.description),
+            data_classes: self.data_classes
+                .as_ref()
+                .map(|classes| classes.iter().map(|c| c.to_string()).collect()),
+            is_verified: self.is_verified,
+            is_sensitive: self.is_sensitive,
+            is_retired: self.is_retired,
+        }
+    }
+}
+
+// Index lists stay sorted, so lookups return breaches in catalogue order.
+fn reindex(indexes: &mut Vec<usize>, index: usize) {
+    if let Err(pos) = indexes.binary_search(&index) {
+        indexes.insert(pos, index);
+    }
+}
+
+fn unindex(indexes: Option<&mut Vec<usize>>, index: usize) {
+    if let Some(indexes) = indexes {
+        indexes.retain(|&i| i != index);
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Catalogue {
+    breaches: Vec<CompactBreach>,
+    interner: Interner,
+    by_name: HashMap<Box<str>, usize>,
+    by_domain: HashMap<String, Vec<usize>>,
+    by_data_class: HashMap<Arc<str>, Vec<usize>>,
+}
+
+impl Catalogue {
+    pub fn new(breaches: &[Breach]) -> Catalogue {
+        let mut catalogue = Catalogue::default();
+        for breach in breaches {
+            catalogue.push(breach);
+        }
+        catalogue
+    }
+
+    // A later breach with the same name replaces the earlier one in place, in every index.
+    pub fn push(&mut self, breach: &Breach) {
+        let compact = CompactBreach::new(breach, &mut self.interner);
+        let index = match self.by_name.get(&compact.name) {
+            Some(&index) => {
+                let old = &self.breaches[index];
+                if let Some(ref domain) = old.domain {
+                    unindex(self.by_domain.get_mut(&domain.to_lowercase()), index);
+                }
+                for class in old.data_classes.iter().flatten() {
+                    unindex(self.by_data_class.get_mut(class), index);
+                }
+                index
+            }
+            None => self.breaches.len(),
+        };
+
+        self.by_name.insert(compact.name.clone(), index);
+        if let Some(ref domain) = compact.domain {
+            reindex(self.by_domain.entry(domain.to_lowercase()).or_default(), index);
+        }
+        for class in compact.data_classes.iter().flatten() {
+            reindex(self.by_data_class.entry(class.clone()).or_default(), index);
+        }
+
+        if index == self.breaches.len() {
+            self.breaches.push(compact);
+        } else {
+            self.breaches[index] = compact;
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&CompactBreach> {
+        self.by_name.get(name).map(|&i| &self.breaches[i])
+    }
+
+    fn lookup<'a>(&'a self, indexes: Option<&'a Vec<usize>>)
+                  -> impl Iterator<Item = &'a CompactBreach> + 'a {
+        indexes.into_iter().flatten().map(move |&i| &self.breaches[i])
+    }
+
+    pub fn by_domain<'a>(&'a self, domain: &str) -> impl Iterator<Item = &'a CompactBreach> + 'a {
+        self.lookup(self.by_domain.get(&domain.to_lowercase()))
+    }
+
+    pub fn by_data_class<'a>(&'a self, class: &str)
+                             -> impl Iterator<Item = &'a CompactBreach> + 'a {
+        self.lookup(self.by_data_class.get(class))
+    }
+
+    pub fn len(&self) -> usize {
+        self.breaches.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.breaches.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, CompactBreach> {
+        self.breaches.iter()
+    }
+
+    pub fn interned_strings(&self) -> usize {
+        self.interner.len()
+    }
+
+    pub fn to_breaches(&self) -> Vec<Breach> {
+        self.breaches.iter().map(CompactBreach::to_breach).collect()
+    }
+}
+
+impl<'a> IntoIterator for &'a Catalogue {
+    type Item = &'a CompactBreach;
+    type IntoIter = std::slice::Iter<'a, CompactBreach>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{breaches_from_str, join_breaches};
+
+    use std::collections::BTreeMap;
+
+    const BODY: &str = r#"[
+        {"Name":"Adobe","Domain":"adobe.com","DataClasses":["Email addresses","Passwords"]},
+        {"Name":"AdobeForums","Domain":"adobe.com","DataClasses":["Email addresses"]},
+        {"Name":"Gawker"}
+    ]"#;
+
+    #[test]
+    fn shares_repeated_strings() {
+        let breaches = breaches_from_str(BODY).unwrap();
+        let catalogue = Catalogue::new(&breaches);
+        let compact: Vec<_> = catalogue.iter().collect();
+
+        assert_eq!(catalogue.interned_strings(), 3);
+        assert!(Arc::ptr_eq(compact[0].domain.as_ref().unwrap(),
+                            compact[1].domain.as_ref().unwrap()));
+        assert!(Arc::ptr_eq(&compact[0].data_classes.as_ref().unwrap()[0],
+                            &compact[1].data_classes.as_ref().unwrap()[0]));
+    }
+
+    #[test]
+    fn indexes_by_name_domain_and_data_class() {
+        let catalogue = Catalogue::new(&breaches_from_str(BODY).unwrap());
+        let names = |found: Vec<&CompactBreach>| -> Vec<String> {
+            found.iter().map(|b| b.name.to_string()).collect()
+        };
+
+        assert_eq!(catalogue.get("Gawker").map(|b| &*b.name), Some("Gawker"));
+        assert!(catalogue.get("gawker").is_none());
+        assert_eq!(names(catalogue.by_domain("Adobe.com").collect()), ["Adobe", "AdobeForums"]);
+        assert_eq!(names(catalogue.by_data_class("Passwords").collect()), ["Adobe"]);
+        assert_eq!(catalogue.by_data_class("Usernames").count(), 0);
+
+        let mut users = BTreeMap::new();
+        users.insert("a@example.com".to_owned(), vec!["Adobe".parse().unwrap()]);
+        users.insert("b@example.com".to_owned(), vec!["Unlisted".parse().unwrap()]);
+        let findings = join_breaches(&users, &catalogue);
+        assert_eq!(findings["a@example.com"].breaches[0].name, "Adobe");
+        assert_eq!(findings["b@example.com"].unknown.len(), 1);
+    }
+
+    #[test]
+    fn duplicate_names_replace_the_earlier_breach() {
+        let mut catalogue = Catalogue::new(&breaches_from_str(BODY).unwrap());
+        let update = r#"[{"Name":"Adobe","Domain":"adobe.net","DataClasses":["Usernames"]}]"#;
+        catalogue.push(&breaches_from_str(update).unwrap()[0]);
+
+        let names = |found: Vec<&CompactBreach>| -> Vec<String> {
+            found.iter().map(|b| b.name.to_string()).collect()
+        };
+        assert_eq!(catalogue.len(), 3);
+        assert_eq!(catalogue.get("Adobe").and_then(|b| b.domain.as_deref()), Some("adobe.net"));
+        assert_eq!(names(catalogue.by_domain("adobe.com").collect()), ["AdobeForums"]);
+        assert_eq!(names(catalogue.by_domain("adobe.net").collect()), ["Adobe"]);
+        assert_eq!(catalogue.by_data_class("Passwords").count(), 0);
+        assert_eq!(names(catalogue.by_data_class("Email addresses").collect()), ["AdobeForums"]);
+        assert_eq!(names(catalogue.by_data_class("Usernames").collect()), ["Adobe"]);
+    }
+
+    #[test]
+    fn round_trips_to_breaches() {
+        let breaches = breaches_from_str(BODY).unwrap();
+        let catalogue = Catalogue::new(&breaches);
+
+        assert_eq!(format!("{:?}", catalogue.to_breaches()), format!("{:?}", breaches));
+    }
+}
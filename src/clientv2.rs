@@ -1,447 +1,1110 @@
-use errors::*;
+use crate::backend::HttpBackend;
+use crate::errors::*;
+use crate::profile::Profile;
+use crate::protocol::{self, AccountQuery, Endpoint, Request, Response};
+use crate::quota::{QuotaTracker, RequestBudget};
+use crate::ratelimit::{HostLimiter, Priority, RateLimiter};
+use crate::redact::{Pseudonymizer, RedactionPolicy};
+use crate::request::{Checked, HibpRequest, Origin, RetryPolicy};
+use crate::schema::{self, Strictness};
+use crate::trail::{AuditEntry, AuditTrail};
+#[cfg(feature = "reqwest")]
+use crate::transport::TransportConfig;
 
-use hyper::net::HttpsConnector;
-use hyper_rustls;
-use hyper::Client as HyperClient;
-use hyper::header::UserAgent;
-use serde_json::{Value, from_str};
 use url::Url;
 
-use std::collections::BTreeMap;
+use std::borrow::Cow;
 use std::io::prelude::*;
-use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 
+pub use crate::model::{Breach, BreachName, DataClass, Paste};
+
+const DEFAULT_MAX_BODY_SIZE: u64 = 16 * 1024 * 1024;
+
+#[derive(Debug)]
 pub struct Clientv2<'a> {
-    client: HyperClient,
-    user_agent: &'a str,
+    backend: Arc<dyn HttpBackend>,
+    #[cfg(feature = "reqwest")]
+    transport: TransportConfig,
+    // Whether `backend` was built from `transport`, so a profile may rebuild it.
+    #[cfg(feature = "reqwest")]
+    transport_backend: bool,
+    user_agent: Cow<'a, str>,
+    max_body_size: u64,
+    lossy_decoding: bool,
+    quota: Arc<QuotaTracker>,
+    rate_limiter: Option<Arc<RateLimiter>>,
+    budget: Option<Arc<RequestBudget>>,
+    host_limiter: Option<Arc<HostLimiter>>,
+    retry_policy: Option<RetryPolicy>,
+    include_unverified: bool,
+    base_url: Option<Url>,
+    validate_schema: bool,
+    schema_strictness: Strictness,
+    redaction: RedactionPolicy,
+    pseudonymizer: Pseudonymizer,
+    trail: Option<Arc<dyn AuditTrail>>,
+    data_classes: OnceLock<Checked<Vec<DataClass>>>,
 }
 
 #[derive(Debug, Clone)]
 pub struct AccountBreachRequest<'a> {
-    client: &'a HyperClient,
-    user_agent: &'a str,
+    client: &'a Clientv2<'a>,
     account: &'a str,
-    truncate: bool,
     domain: Option<&'a str>,
+    include_unverified: bool,
+    priority: Priority,
+    client_retry: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct TruncatedAccountBreachRequest<'a> {
+    inner: AccountBreachRequest<'a>,
 }
 
 #[derive(Debug, Clone)]
 pub struct AllBreachesRequest<'a> {
-    client: &'a HyperClient,
-    user_agent: &'a str,
+    client: &'a Clientv2<'a>,
     domain: Option<&'a str>,
+    priority: Priority,
+    client_retry: bool,
 }
 
 #[derive(Debug, Clone)]
 pub struct BreachRequest<'a> {
-    client: &'a HyperClient,
-    user_agent: &'a str,
-    name: &'a str,
+    client: &'a Clientv2<'a>,
+    name: BreachName,
+    priority: Priority,
+    client_retry: bool,
 }
 
 #[derive(Debug, Clone)]
 pub struct DataClassRequest<'a> {
-    client: &'a HyperClient,
-    user_agent: &'a str,
+    client: &'a Clientv2<'a>,
+    priority: Priority,
+    client_retry: bool,
 }
 
 #[derive(Debug, Clone)]
 pub struct PasteRequest<'a> {
-    client: &'a HyperClient,
-    user_agent: &'a str,
+    client: &'a Clientv2<'a>,
     account: &'a str,
+    priority: Priority,
+    client_retry: bool,
 }
 
-#[derive(Debug, Clone)]
-pub struct Breach {
-    name: String,
-    title: Option<String>,
-    domain: Option<String>,
-    breach_date: Option<String>,
-    added_date: Option<String>,
-    pwn_count: Option<u64>,
-    description: Option<String>,
-    data_classes: Option<Vec<String>>,
-    is_verified: Option<bool>,
-    is_sensitive: Option<bool>,
-    is_retired: Option<bool>,
+#[cfg(feature = "reqwest")]
+pub(crate) fn default_backend() -> Arc<dyn HttpBackend> {
+    Arc::new(crate::backend::ReqwestBackend::default())
 }
 
-#[derive(Debug, Clone)]
-pub struct Paste {
-    source: String,
-    id: String,
-    title: Option<String>,
-    date: Option<String>,
-    email_count: u64,
+#[cfg(all(feature = "ureq", not(feature = "reqwest")))]
+pub(crate) fn default_backend() -> Arc<dyn HttpBackend> {
+    Arc::new(crate::backend::UreqBackend::default())
 }
 
-fn get_serde_string(obj: &Value) -> Result<String> {
-    match obj.as_str() {
-        Some(s) => Ok(s.to_owned()),
-        None => Err(format!("Failed to parse value to string: {:#?}", obj).into()),
+impl<'a> Clientv2<'a> {
+    #[cfg(any(feature = "reqwest", feature = "ureq"))]
+    pub fn new<U: Into<Cow<'a, str>>>(user_agent: U) -> Clientv2<'a> {
+        let client = Clientv2::with_backend(user_agent, default_backend());
+        #[cfg(feature = "reqwest")]
+        let client = Clientv2 {
+            transport_backend: true,
+            ..client
+        };
+        client
     }
-}
 
-fn get_serde_array(obj: &Value) -> Result<Vec<Value>> {
-    match obj.as_array() {
-        Some(s) => Ok(s.to_owned()),
-        None => Err(format!("Failed to parse value to array: {:#?}", obj).into()),
+    pub fn with_backend<U>(user_agent: U, backend: Arc<dyn HttpBackend>) -> Clientv2<'a>
+        where U: Into<Cow<'a, str>>
+    {
+        Clientv2 {
+            backend,
+            #[cfg(feature = "reqwest")]
+            transport: TransportConfig::default(),
+            #[cfg(feature = "reqwest")]
+            transport_backend: false,
+            user_agent: user_agent.into(),
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            lossy_decoding: false,
+            quota: Arc::new(QuotaTracker::new(Duration::from_secs(60), None)),
+            rate_limiter: None,
+            budget: None,
+            host_limiter: None,
+            retry_policy: None,
+            include_unverified: false,
+            base_url: None,
+            validate_schema: false,
+            schema_strictness: Strictness::default(),
+            redaction: RedactionPolicy::default(),
+            pseudonymizer: Pseudonymizer::default(),
+            trail: None,
+            data_classes: OnceLock::new(),
+        }
     }
-}
 
-fn get_serde_u64(obj: &Value) -> Result<u64> {
-    match obj.as_u64() {
-        Some(s) => Ok(s),
-        None => Err(format!("Failed to parse value to u64: {:#?}", obj).into()),
+    pub fn set_base_url(&mut self, base: &str) -> Result<&mut Self> {
+        self.base_url = Some(protocol::parse_base_url(base)?);
+        Ok(self)
     }
-}
 
-fn get_serde_bool(obj: &Value) -> Result<bool> {
-    match obj.as_bool() {
-        Some(s) => Ok(s),
-        None => Err(format!("Failed to parse value to bool: {:#?}", obj).into()),
+    pub fn base_url(&self) -> Option<&Url> {
+        self.base_url.as_ref()
     }
-}
 
-// fn get_serde_object<'a>(obj: &'a Value) -> Result<&'a BTreeMap<String, Value>> {
-//     match obj.as_object() {
-//         Some(s) => Ok(s),
-//         None => Err(format!("Failed to parse value to object: {:#?}", obj).into()),
-//     }
-// }
+    fn build_request(&self, endpoint: Endpoint, url: Url) -> Request {
+        let url = match self.base_url {
+            Some(ref base) => protocol::rebase(&url, base),
+            None => url,
+        };
+        Request::get(&self.user_agent, endpoint, url)
+    }
 
-fn get_or_err<'a>(name: &str, obj: &'a BTreeMap<String, Value>) -> Result<&'a Value> {
-    match obj.get(name) {
-        Some(n) => Ok(n),
-        None => Err(format!("Failed to get field: {:?}", name).into()),
+    pub fn set_schema_validation(&mut self, validate: bool) -> &mut Self {
+        self.validate_schema = validate;
+        self
+    }
+
+    pub fn set_schema_strictness(&mut self, strictness: Strictness) -> &mut Self {
+        self.schema_strictness = strictness;
+        self
+    }
+
+    pub fn set_backend(&mut self, backend: Arc<dyn HttpBackend>) -> &mut Self {
+        self.backend = backend;
+        #[cfg(feature = "reqwest")]
+        {
+            self.transport_backend = false;
+        }
+        self
+    }
+
+    #[cfg(feature = "reqwest")]
+    pub fn set_proxy(&mut self, proxy: &str) -> Result<&mut Self> {
+        let mut transport = self.transport.clone();
+        transport.set_proxy(proxy)?;
+        self.set_transport(transport)
+    }
+
+    #[cfg(feature = "reqwest")]
+    pub fn set_transport(&mut self, transport: TransportConfig) -> Result<&mut Self> {
+        self.backend = Arc::new(crate::backend::ReqwestBackend(transport.build()?));
+        self.transport = transport;
+        self.transport_backend = true;
+        Ok(self)
+    }
+
+    #[cfg(feature = "reqwest")]
+    pub fn transport(&self) -> &TransportConfig {
+        &self.transport
+    }
+
+    pub fn set_max_body_size(&mut self, limit: u64) -> &mut Self {
+        self.max_body_size = limit;
+        self
+    }
+
+    pub fn set_lossy_decoding(&mut self, lossy: bool) -> &mut Self {
+        self.lossy_decoding = lossy;
+        self
+    }
+
+    pub fn set_quota(&mut self, window: Duration, limit: Option<u32>) -> &mut Self {
+        self.quota = Arc::new(QuotaTracker::new(window, limit));
+        self
+    }
+
+    pub fn set_quota_tracker(&mut self, quota: Arc<QuotaTracker>) -> &mut Self {
+        self.quota = quota;
+        self
+    }
+
+    pub fn set_rate_limiter(&mut self, limiter: Arc<RateLimiter>) -> &mut Self {
+        self.rate_limiter = Some(limiter);
+        self
+    }
+
+    // Fills in only what the caller hasn't configured, so a profile can be applied at any point.
+    // A custom backend is kept as is; the connect timeout applies to the built-in transport.
+    pub fn apply_profile(&mut self, profile: Profile) -> Result<&mut Self> {
+        if self.rate_limiter.is_none() {
+            self.set_rate_limiter(Arc::new(RateLimiter::new(profile.request_interval())));
+        }
+        if self.host_limiter.is_none() {
+            self.set_max_in_flight_per_host(profile.max_in_flight_per_host());
+        }
+        if self.retry_policy.is_none() {
+            self.set_retry_policy(profile.retry_policy());
+        }
+
+        #[cfg(feature = "reqwest")]
+        {
+            if self.transport_backend && self.transport.connect_timeout().is_none() {
+                let mut transport = self.transport.clone();
+                transport.set_connect_timeout(profile.connect_timeout());
+                self.set_transport(transport)?;
+            }
+        }
+        Ok(self)
+    }
+
+    // Applied to every request the client sends; `with_retry` still wraps individual requests.
+    pub fn set_retry_policy(&mut self, policy: RetryPolicy) -> &mut Self {
+        self.retry_policy = Some(policy);
+        self
+    }
+
+    pub fn retry_policy(&self) -> Option<&RetryPolicy> {
+        self.retry_policy.as_ref()
+    }
+
+    pub fn set_budget(&mut self, budget: Arc<RequestBudget>) -> &mut Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    pub fn set_host_limiter(&mut self, limiter: Arc<HostLimiter>) -> &mut Self {
+        self.host_limiter = Some(limiter);
+        self
+    }
+
+    pub fn set_max_in_flight_per_host(&mut self, max_in_flight: usize) -> &mut Self {
+        self.set_host_limiter(Arc::new(HostLimiter::new(max_in_flight)))
+    }
+
+    pub fn set_default_include_unverified(&mut self, include: bool) -> &mut Self {
+        self.include_unverified = include;
+        self
+    }
+
+    pub fn set_redaction_policy(&mut self, policy: RedactionPolicy) -> &mut Self {
+        self.redaction = policy;
+        self
+    }
+
+    pub fn set_pseudonym_key(&mut self, key: &[u8]) -> &mut Self {
+        self.pseudonymizer = Pseudonymizer::new(key);
+        self
+    }
+
+    pub fn set_pseudonymizer(&mut self, pseudonymizer: Pseudonymizer) -> &mut Self {
+        self.pseudonymizer = pseudonymizer;
+        self
+    }
+
+    pub fn pseudonymizer(&self) -> &Pseudonymizer {
+        &self.pseudonymizer
+    }
+
+    pub fn pseudonym(&self, account: &str) -> String {
+        self.pseudonymizer.id(account)
+    }
+
+    pub fn set_audit_trail(&mut self, trail: Arc<dyn AuditTrail>) -> &mut Self {
+        self.trail = Some(trail);
+        self
+    }
+
+    pub fn redaction_policy(&self) -> RedactionPolicy {
+        self.redaction
+    }
+
+    pub fn rate_limiter(&self) -> Option<&Arc<RateLimiter>> {
+        self.rate_limiter.as_ref()
+    }
+
+    pub fn budget(&self) -> Option<&Arc<RequestBudget>> {
+        self.budget.as_ref()
+    }
+
+    pub fn host_limiter(&self) -> Option<&Arc<HostLimiter>> {
+        self.host_limiter.as_ref()
+    }
+
+    pub fn quota(&self) -> &QuotaTracker {
+        &self.quota
     }
-}
 
-fn parse_breach(obj: &BTreeMap<String, Value>) -> Result<Breach> {
-    Ok(Breach {
-        name: try!(get_serde_string(try!(get_or_err("Name", obj)))),
-        title: try!(obj.get("Title").map(get_serde_string).map_or(Ok(None), |t| t.map(Some))),
-        domain: try!(obj.get("Domain")
-            .map(get_serde_string)
-            .map_or(Ok(None), |t| t.map(Some))),
-        breach_date: try!(obj.get("BreachDate")
-            .map(get_serde_string)
-            .map_or(Ok(None), |t| t.map(Some))),
-        added_date: try!(obj.get("AddedDate")
-            .map(get_serde_string)
-            .map_or(Ok(None), |t| t.map(Some))),
-        pwn_count: try!(obj.get("PwnCount")
-            .map(get_serde_u64)
-            .map_or(Ok(None), |t| t.map(Some))),
-        description: try!(obj.get("Description")
-            .map(get_serde_string)
-            .map_or(Ok(None), |t| t.map(Some))),
-        data_classes: try!(obj.get("DataClasses")
-            .map(|dc| {
-                let v = try!(get_serde_array(dc));
-                v.iter()
-                    .map(get_serde_string)
-                    .collect::<Result<Vec<_>>>()
+    pub(crate) fn validate_account(&self, account: &str) -> Result<()> {
+        protocol::validate_account(account).map_err(|e| self.redaction.redact_error(e))
+    }
+
+    // Error pages have their own shape, so only successful bodies are validated; the parser then
+    // reports the status.
+    fn check_schema(&self, request: &Request, response: &Response) -> Result<()> {
+        if !self.validate_schema || !(200..300).contains(&response.status) {
+            return Ok(());
+        }
+
+        let mut mismatches = schema::validate(request, &response.body);
+        mismatches.retain(|m| self.schema_strictness.rejects(m));
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::SchemaMismatch {
+                endpoint: request.endpoint.as_str(),
+                mismatches,
             })
-            .map_or(Ok(None), |t| t.map(Some))),
-        is_verified: try!(obj.get("IsVerified")
-            .map(get_serde_bool)
-            .map_or(Ok(None), |t| t.map(Some))),
-        is_sensitive: try!(obj.get("IsSensitive")
-            .map(get_serde_bool)
-            .map_or(Ok(None), |t| t.map(Some))),
-        is_retired: try!(obj.get("IsRetired")
-            .map(get_serde_bool)
-            .map_or(Ok(None), |t| t.map(Some))),
-    })
-}
+        }
+    }
 
-fn breaches_from_str(s: &str) -> Result<Vec<Breach>> {
-    let data: Value = try!(from_str(&s)
-        .chain_err(|| format!("Failed to parse breaches: {:#?}", s)));
-
-    if let Some(data) = data.as_array() {
-        data.iter()
-            .map(|d| d.as_object())
-            .collect::<Option<Vec<_>>>()
-            .map_or(Err(format!("Failed to convert internal object from response: {:#?}",
-                                data)
-                        .into()),
-                    |o| {
-                        o.into_iter()
-                            .map(parse_breach)
-                            .collect::<Result<Vec<_>>>()
-                    })
-    } else if let Some(data) = data.as_object() {
-        vec![parse_breach(&data)].into_iter().collect()
-    } else {
-        Err(format!("Improperly formatted response: {:#?}", s).into())
+    // `client_retry` is false when `with_retry` drives the attempts, so retries never nest.
+    fn fetch<T, F>(&self,
+                   request: Request,
+                   priority: Priority,
+                   client_retry: bool,
+                   parse: F)
+                   -> Result<Checked<T>>
+        where F: Fn(&Response) -> Result<T>
+    {
+        match self.retry_policy {
+            Some(ref policy) if client_retry => {
+                policy.run(|| self.fetch_once(&request, priority, &parse))
+            }
+            _ => self.fetch_once(&request, priority, &parse),
+        }
     }
-}
 
-fn parse_paste(obj: &BTreeMap<String, Value>) -> Result<Paste> {
-    Ok(Paste {
-        source: try!(get_serde_string(try!(get_or_err("Source", obj)))),
-        id: try!(get_serde_string(try!(get_or_err("Id", obj)))),
-        title: try!(get_or_err("Title", obj)).as_str().map(String::from),
-        date: try!(get_or_err("Date", obj)).as_str().map(String::from),
-        email_count: try!(get_serde_u64(try!(get_or_err("EmailCount", obj)))),
-    })
-}
+    fn fetch_once<T, F>(&self,
+                        request: &Request,
+                        priority: Priority,
+                        parse: &F)
+                        -> Result<Checked<T>>
+        where F: Fn(&Response) -> Result<T>
+    {
+        let response = self.execute(request, priority)?;
+
+        match self.check_schema(request, &response).and_then(|_| parse(&response)) {
+            Ok(value) => {
+                Ok(Checked {
+                    debug_headers: response.debug_headers,
+                    ..Checked::network(value)
+                })
+            }
+            Err(e) => {
+                Err(Error::InvalidResponse {
+                    context: ResponseContext {
+                        debug_headers: response.debug_headers.clone(),
+                        ..ResponseContext::new(request.endpoint.as_str(),
+                                               request.redact_url(self.redaction),
+                                               response.status,
+                                               &response.body)
+                    },
+                    source: Box::new(e),
+                })
+            }
+        }
+    }
 
-fn pastes_from_str(s: &str) -> Result<Vec<Paste>> {
-    let data: Value = try!(from_str(&s).chain_err(|| format!("Failed to parse pastes: {:#?}", s)));
-
-    match data.as_array() {
-        Some(data) => {
-            data.iter()
-                .map(|d| d.as_object())
-                .collect::<Option<Vec<_>>>()
-                .map_or(Err(format!("Failed to convert internal object from response: {:#?}",
-                                    data)
-                            .into()),
-                        |o| {
-                            o.into_iter()
-                                .map(parse_paste)
-                                .collect::<Result<Vec<_>>>()
-                        })
+    fn execute(&self, request: &Request, priority: Priority) -> Result<Response> {
+        let mut status = None;
+        let result = self.send_request(request, priority, &mut status);
+
+        if let Some(ref trail) = self.trail {
+            let entry = AuditEntry::pseudonymized(request, status, &result, &self.pseudonymizer);
+            trail.record(&entry)?;
         }
-        None => Err(format!("Improperly formatted response: {:#?}", s).into()),
+        result
     }
-}
 
-impl<'a> Clientv2<'a> {
-    pub fn new(user_agent: &'a str) -> Clientv2 {
-        Clientv2 {
-            client:
-                HyperClient::with_connector(HttpsConnector::new(hyper_rustls::TlsClient::new())),
-            user_agent: user_agent,
+    fn send_request(&self,
+                    request: &Request,
+                    priority: Priority,
+                    status_out: &mut Option<u16>)
+                    -> Result<Response> {
+        if let Some(ref budget) = self.budget {
+            budget.spend()?;
+        }
+
+        if let Some(ref limiter) = self.rate_limiter {
+            limiter.acquire_with(priority);
         }
+
+        let _permit = self.host_limiter
+            .as_ref()
+            .map(|limiter| limiter.acquire(request.url.host_str().unwrap_or("")));
+
+        let res = self.backend
+            .get(request)
+            .map_err(|e| {
+                Error::Transport {
+                    endpoint: request.endpoint.as_str(),
+                    url: request.redact_url(self.redaction),
+                    source: e,
+                }
+            })?;
+        self.quota.record_request();
+
+        let status = res.status;
+        *status_out = Some(status);
+
+        if status == 429 {
+            let retry_after = res.retry_after.as_deref().and_then(protocol::parse_retry_after);
+            self.quota.record_rate_limited(retry_after);
+            if let (Some(limiter), Some(delay)) = (self.rate_limiter.as_ref(), retry_after) {
+                limiter.defer(delay);
+            }
+
+            return Err(Error::RateLimited(retry_after.map(|r| r.as_secs()), res.debug_headers));
+        }
+
+        if res.content_length.is_some_and(|len| len > self.max_body_size) {
+            return Err(Error::ResponseTooLarge(self.max_body_size));
+        }
+
+        let mut bytes = vec![];
+        res.body
+            .take(self.max_body_size + 1)
+            .read_to_end(&mut bytes)
+            .map_err(Error::Read)?;
+        if bytes.len() as u64 > self.max_body_size {
+            return Err(Error::ResponseTooLarge(self.max_body_size));
+        }
+
+        let r = protocol::decode_body(bytes, res.content_type.as_deref(), self.lossy_decoding)?;
+
+        protocol::check_content(status, res.content_type.as_deref(), &r)
+            .map_err(|e| e.with_debug_headers(&res.debug_headers))?;
+        Ok(Response {
+            status,
+            body: r,
+            debug_headers: res.debug_headers,
+        })
     }
 
     pub fn get_breaches_acct(&'a self, acct: &'a str) -> AccountBreachRequest<'a> {
         AccountBreachRequest {
-            client: &self.client,
-            user_agent: &self.user_agent,
+            client: self,
             account: acct,
-            truncate: false,
             domain: None,
+            include_unverified: self.include_unverified,
+            priority: Priority::default(),
+            client_retry: true,
         }
     }
 
     pub fn get_breaches(&'a self) -> AllBreachesRequest<'a> {
         AllBreachesRequest {
-            client: &self.client,
-            user_agent: &self.user_agent,
+            client: self,
             domain: None,
+            priority: Priority::default(),
+            client_retry: true,
         }
     }
 
-    pub fn get_breach(&'a self, name: &'a str) -> BreachRequest<'a> {
+    pub fn get_breach(&'a self, name: BreachName) -> BreachRequest<'a> {
         BreachRequest {
-            client: &self.client,
-            user_agent: &self.user_agent,
-            name: name,
+            client: self,
+            name,
+            priority: Priority::default(),
+            client_retry: true,
         }
     }
 
     pub fn get_data_classes(&'a self) -> DataClassRequest<'a> {
         DataClassRequest {
-            client: &self.client,
-            user_agent: &self.user_agent,
+            client: self,
+            priority: Priority::default(),
+            client_retry: true,
         }
     }
 
     pub fn get_pastes(&'a self, account: &'a str) -> PasteRequest<'a> {
         PasteRequest {
-            client: &self.client,
-            user_agent: &self.user_agent,
-            account: &account,
+            client: self,
+            account,
+            priority: Priority::default(),
+            client_retry: true,
         }
     }
 }
 
 impl<'a> AccountBreachRequest<'a> {
-    pub fn set_truncate(&mut self, t: bool) -> &mut Self {
-        self.truncate = t;
+    pub fn set_include_unverified(mut self, include: bool) -> Self {
+        self.include_unverified = include;
         self
     }
 
-    pub fn set_domain(&mut self, d: &'a str) -> &mut Self {
+    fn query(&self, truncate: bool) -> AccountQuery<'a> {
+        AccountQuery {
+            domain: self.domain,
+            truncate,
+            include_unverified: self.include_unverified,
+        }
+    }
+
+    pub fn truncated(self) -> TruncatedAccountBreachRequest<'a> {
+        TruncatedAccountBreachRequest { inner: self }
+    }
+
+    pub fn set_domain(mut self, d: &'a str) -> Self {
         self.domain = Some(d);
         self
     }
 
-    fn build_url(&self) -> Url {
-        let mut base = String::new();
-        base.push_str("https://haveibeenpwned.com/api/v2/breachedaccount/");
-        base.push_str(self.account);
+    pub fn set_priority(mut self, p: Priority) -> Self {
+        self.priority = p;
+        self
+    }
 
-        let mut url = Url::parse(&base).unwrap();
+    pub fn request(&self) -> Request {
+        self.client.build_request(Endpoint::BreachedAccount,
+                                  protocol::breached_account_url(self.account, self.query(false)))
+    }
 
-        if let Some(d) = self.domain {
-            url.query_pairs_mut().append_pair("domain", d);
-        }
+    pub fn send(self) -> Result<Vec<Breach>> {
+        self.send_checked().map(|checked| checked.value)
+    }
 
-        if self.truncate {
-            url.query_pairs_mut().append_pair("truncateResponse", "true");
-        }
-        url
+    pub fn send_checked(self) -> Result<Checked<Vec<Breach>>> {
+        self.client.validate_account(self.account)?;
+        self.client.fetch(self.request(),
+                          self.priority,
+                          self.client_retry,
+                          protocol::parse_breaches)
     }
+}
 
-    pub fn send(&mut self) -> Result<Vec<Breach>> {
-        let url = self.build_url();
+impl<'a> TruncatedAccountBreachRequest<'a> {
+    pub fn set_domain(self, d: &'a str) -> Self {
+        self.inner.set_domain(d).truncated()
+    }
 
-        let mut res = try!(self.client
-            .get(url.clone())
-            .header(UserAgent(self.user_agent.to_owned()))
-            .send()
-            .chain_err(|| {
-                format!("Failed to send GET request for AccountBreach for url {:#?}",
-                        url)
-            }));
+    pub fn set_include_unverified(self, include: bool) -> Self {
+        self.inner.set_include_unverified(include).truncated()
+    }
 
-        let mut r = String::new();
-        try!(res.read_to_string(&mut r).chain_err(|| "Failed to read response to string"));
-        breaches_from_str(&r)
+    pub fn set_priority(self, p: Priority) -> Self {
+        self.inner.set_priority(p).truncated()
+    }
+
+    pub fn request(&self) -> Request {
+        let inner = &self.inner;
+        inner.client.build_request(Endpoint::BreachedAccount,
+                                   protocol::breached_account_url(inner.account, inner.query(true)))
+    }
+
+    pub fn send(self) -> Result<Vec<BreachName>> {
+        self.send_checked().map(|checked| checked.value)
     }
-}
 
+    pub fn send_checked(self) -> Result<Checked<Vec<BreachName>>> {
+        self.inner.client.validate_account(self.inner.account)?;
+        self.inner
+            .client
+            .fetch(self.request(),
+                   self.inner.priority,
+                   self.inner.client_retry,
+                   protocol::parse_breach_names)
+    }
+}
 
 impl<'a> AllBreachesRequest<'a> {
-    pub fn set_domain(&mut self, d: &'a str) -> &mut Self {
+    pub fn set_domain(mut self, d: &'a str) -> Self {
         self.domain = Some(d);
         self
     }
 
-    fn build_url(&self) -> Url {
-        let mut url = Url::parse("https://haveibeenpwned.com/api/v2/breaches").unwrap();
+    pub fn set_priority(mut self, p: Priority) -> Self {
+        self.priority = p;
+        self
+    }
 
-        if let Some(d) = self.domain {
-            url.query_pairs_mut().append_pair("domain", d);
+    pub fn request(&self) -> Request {
+        self.client.build_request(Endpoint::Breaches,
+                                  protocol::breaches_url(self.domain))
+    }
+
+    pub fn send(self) -> Result<Vec<Breach>> {
+        self.send_checked().map(|checked| checked.value)
+    }
+
+    pub fn send_checked(self) -> Result<Checked<Vec<Breach>>> {
+        self.client.fetch(self.request(),
+                          self.priority,
+                          self.client_retry,
+                          protocol::parse_breaches)
+    }
+}
+
+
+impl<'a> BreachRequest<'a> {
+    pub fn set_priority(mut self, p: Priority) -> Self {
+        self.priority = p;
+        self
+    }
+
+    pub fn request(&self) -> Request {
+        self.client.build_request(Endpoint::Breach,
+                                  protocol::breach_url(self.name.as_str()))
+    }
+
+    pub fn send(self) -> Result<Option<Breach>> {
+        self.send_checked().map(|checked| checked.value)
+    }
+
+    pub fn send_checked(self) -> Result<Checked<Option<Breach>>> {
+        self.client.fetch(self.request(), self.priority, self.client_retry, protocol::parse_breach)
+    }
+}
+
+impl<'a> DataClassRequest<'a> {
+    pub fn set_priority(mut self, p: Priority) -> Self {
+        self.priority = p;
+        self
+    }
+
+    pub fn request(&self) -> Request {
+        self.client.build_request(Endpoint::DataClasses,
+                                  protocol::data_classes_url())
+    }
+
+    pub fn send(self) -> Result<Vec<DataClass>> {
+        self.send_checked().map(|checked| checked.value)
+    }
+
+    pub fn send_checked(self) -> Result<Checked<Vec<DataClass>>> {
+        if let Some(cached) = self.client.data_classes.get() {
+            return Ok(Checked { origin: Origin::Cache, ..cached.clone() });
         }
 
-        url
+        let data_classes = self.client
+            .fetch(self.request(), self.priority, self.client_retry, protocol::parse_data_classes)?;
+        Ok(self.client.data_classes.get_or_init(|| data_classes).clone())
     }
+}
 
-    pub fn send(&mut self) -> Result<Vec<Breach>> {
-        let url = self.build_url();
+impl<'a> PasteRequest<'a> {
+    pub fn set_priority(mut self, p: Priority) -> Self {
+        self.priority = p;
+        self
+    }
 
-        let mut res = try!(self.client
-            .get(url.clone())
-            .header(UserAgent(self.user_agent.to_owned()))
-            .send()
-            .chain_err(|| {
-                format!("Failed to send GET request for AllBreaches for url: {}",
-                        url)
-            }));
+    pub fn request(&self) -> Request {
+        self.client.build_request(Endpoint::PasteAccount,
+                                  protocol::paste_account_url(self.account))
+    }
 
-        let mut r = String::new();
-        try!(res.read_to_string(&mut r).chain_err(|| "Failed to read response to string"));
-        breaches_from_str(&r)
+    pub fn send(self) -> Result<Vec<Paste>> {
+        self.send_checked().map(|checked| checked.value)
+    }
+
+    pub fn send_checked(self) -> Result<Checked<Vec<Paste>>> {
+        self.client.validate_account(self.account)?;
+        self.client.fetch(self.request(), self.priority, self.client_retry, protocol::parse_pastes)
     }
 }
 
+impl<'a> HibpRequest for AccountBreachRequest<'a> {
+    type Output = Vec<Breach>;
 
-impl<'a> BreachRequest<'a> {
-    fn build_url(&self, name: &str) -> String {
-        let mut url = String::with_capacity(43 + name.len());
+    fn request(&self) -> Request {
+        AccountBreachRequest::request(self)
+    }
 
-        url.push_str("https://haveibeenpwned.com/api/v2/breach/");
-        url.push_str(name);
+    fn send(self) -> Result<Vec<Breach>> {
+        AccountBreachRequest::send(self)
+    }
 
-        url
+    fn send_checked(self) -> Result<Checked<Vec<Breach>>> {
+        AccountBreachRequest::send_checked(self)
     }
 
-    pub fn send(&mut self) -> Result<Vec<Breach>> {
-        let url = self.build_url(&self.name);
+    fn send_once(mut self) -> Result<Checked<Vec<Breach>>> {
+        self.client_retry = false;
+        AccountBreachRequest::send_checked(self)
+    }
+}
 
-        let mut res = try!(self.client
-            .get(&url)
-            .header(UserAgent(self.user_agent.to_owned()))
-            .send()
-            .chain_err(|| "Failed to sent GET request for Breach"));
+impl<'a> HibpRequest for TruncatedAccountBreachRequest<'a> {
+    type Output = Vec<BreachName>;
 
-        let mut r = String::new();
-        try!(res.read_to_string(&mut r).chain_err(|| "Failed to read response to string"));
+    fn request(&self) -> Request {
+        TruncatedAccountBreachRequest::request(self)
+    }
 
-        breaches_from_str(&r)
+    fn send(self) -> Result<Vec<BreachName>> {
+        TruncatedAccountBreachRequest::send(self)
+    }
+
+    fn send_checked(self) -> Result<Checked<Vec<BreachName>>> {
+        TruncatedAccountBreachRequest::send_checked(self)
+    }
+
+    fn send_once(mut self) -> Result<Checked<Vec<BreachName>>> {
+        self.inner.client_retry = false;
+        TruncatedAccountBreachRequest::send_checked(self)
     }
 }
 
-impl<'a> DataClassRequest<'a> {
-    pub fn send(&mut self) -> Result<Vec<String>> {
-        let mut res = try!(self.client
-            .get("https://haveibeenpwned.com/api/v2/dataclasses")
-            .header(UserAgent(self.user_agent.to_owned()))
-            .send()
-            .chain_err(|| "Failed to sent GET request for Breach"));
+impl<'a> HibpRequest for AllBreachesRequest<'a> {
+    type Output = Vec<Breach>;
 
-        let mut r = String::new();
-        try!(res.read_to_string(&mut r).chain_err(|| "Failed to read response to string"));
+    fn request(&self) -> Request {
+        AllBreachesRequest::request(self)
+    }
 
+    fn send(self) -> Result<Vec<Breach>> {
+        AllBreachesRequest::send(self)
+    }
 
+    fn send_checked(self) -> Result<Checked<Vec<Breach>>> {
+        AllBreachesRequest::send_checked(self)
+    }
 
-        let data: Value = try!(from_str(&r)
-            .chain_err(|| format!("Failed to parse data classes: {:#?}", r)));
+    fn send_once(mut self) -> Result<Checked<Vec<Breach>>> {
+        self.client_retry = false;
+        AllBreachesRequest::send_checked(self)
+    }
+}
 
-        data.as_array()
-            .map(|d| {
-                d.into_iter()
-                    .map(get_serde_string)
-                    .collect::<Result<Vec<_>>>()
-            })
-            .unwrap_or(Err((format!("Failed to parse DataClass into array of string: {}", data)
-                .into())))
+impl<'a> HibpRequest for BreachRequest<'a> {
+    type Output = Option<Breach>;
+
+    fn request(&self) -> Request {
+        BreachRequest::request(self)
+    }
+
+    fn send(self) -> Result<Option<Breach>> {
+        BreachRequest::send(self)
+    }
+
+    fn send_checked(self) -> Result<Checked<Option<Breach>>> {
+        BreachRequest::send_checked(self)
+    }
+
+    fn send_once(mut self) -> Result<Checked<Option<Breach>>> {
+        self.client_retry = false;
+        BreachRequest::send_checked(self)
     }
 }
 
-impl<'a> PasteRequest<'a> {
-    fn build_url(&self) -> Url {
-        Url::from_str(&format!("https://haveibeenpwned.com/api/v2/pasteaccount/{}",
-                               self.account))
-            .unwrap()
+impl<'a> HibpRequest for DataClassRequest<'a> {
+    type Output = Vec<DataClass>;
+
+    fn request(&self) -> Request {
+        DataClassRequest::request(self)
     }
 
-    pub fn send(&mut self) -> Result<Vec<Paste>> {
-        let url = self.build_url();
-        let mut res = try!(self.client
-            .get(url)
-            .header(UserAgent(self.user_agent.to_owned()))
-            .send()
-            .chain_err(|| "Failed to sent GET request for pastes"));
+    fn send(self) -> Result<Vec<DataClass>> {
+        DataClassRequest::send(self)
+    }
 
-        let mut r = String::new();
-        try!(res.read_to_string(&mut r).chain_err(|| "Failed to read response to string"));
-        if r.is_empty() {
-            Ok(vec![])
-        } else {
-            pastes_from_str(&r)
-        }
+    fn send_checked(self) -> Result<Checked<Vec<DataClass>>> {
+        DataClassRequest::send_checked(self)
+    }
+
+    fn send_once(mut self) -> Result<Checked<Vec<DataClass>>> {
+        self.client_retry = false;
+        DataClassRequest::send_checked(self)
+    }
+}
+
+impl<'a> HibpRequest for PasteRequest<'a> {
+    type Output = Vec<Paste>;
+
+    fn request(&self) -> Request {
+        PasteRequest::request(self)
+    }
+
+    fn send(self) -> Result<Vec<Paste>> {
+        PasteRequest::send(self)
+    }
+
+    fn send_checked(self) -> Result<Checked<Vec<Paste>>> {
+        PasteRequest::send_checked(self)
+    }
+
+    fn send_once(mut self) -> Result<Checked<Vec<Paste>>> {
+        self.client_retry = false;
+        PasteRequest::send_checked(self)
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, any(feature = "reqwest", feature = "ureq")))]
 mod tests {
     use super::*;
+    use crate::backend::{BoxError, HttpResponse};
+    use crate::clock::MockClock;
+
+    use std::io::Cursor;
+    use std::sync::atomic::{AtomicUsize, Ordering};
 
     #[test]
-    fn it_works() {
+    fn builders_chain_by_value() {
+        let client = Clientv2::new("test-rust-client");
+
+        let request = client.get_breaches_acct("test@example.com")
+            .set_domain("adobe.com")
+            .truncated()
+            .set_priority(Priority::High)
+            .request();
+
+        assert_eq!(request.url.query(), Some("domain=adobe.com&truncateResponse=true"));
+    }
+
+    #[test]
+    fn client_default_include_unverified() {
+        let mut client = Clientv2::new("test-rust-client");
+        client.set_default_include_unverified(true);
+
+        let request = client.get_breaches_acct("test@example.com").request();
+        assert_eq!(request.url.query(), Some("includeUnverified=true"));
+
+        let request = client.get_breaches_acct("test@example.com")
+            .set_include_unverified(false)
+            .request();
+        assert_eq!(request.url.query(), None);
+    }
+
+    fn url_of<R: HibpRequest>(request: &R) -> String {
+        request.request().url.to_string()
+    }
+
+    #[test]
+    fn requests_share_a_trait() {
+        let client = Clientv2::new("test-rust-client");
+
+        assert_eq!(url_of(&client.get_data_classes()),
+                   "https://haveibeenpwned.com/api/v2/dataclasses");
+        assert_eq!(url_of(&client.get_breach("Adobe".parse().unwrap())),
+                   "https://haveibeenpwned.com/api/v2/breach/Adobe");
+    }
+
+    #[derive(Debug)]
+    struct Canned(u16, Option<&'static str>, &'static str);
+
+    impl HttpBackend for Canned {
+        fn get(&self, _request: &Request) -> std::result::Result<HttpResponse, BoxError> {
+            Ok(HttpResponse {
+                status: self.0,
+                content_type: Some("application/json".to_owned()),
+                retry_after: self.1.map(String::from),
+                content_length: None,
+                debug_headers: vec![("cf-ray".to_owned(), "8a1b2c3d4e5f6a7b-AMS".to_owned())],
+                body: Box::new(Cursor::new(self.2)),
+            })
+        }
+    }
+
+    #[test]
+    fn backends_are_pluggable() {
+        let client = Clientv2::with_backend("test-rust-client",
+                                            Arc::new(Canned(200, None, r#"["Email addresses"]"#)));
+        let first = client.get_data_classes().send_checked().unwrap();
+        assert_eq!(first.value, vec![DataClass::EmailAddresses]);
+        assert_eq!(first.origin, Origin::Network);
+
+        let second = client.get_data_classes().send_checked().unwrap();
+        assert_eq!((second.origin, second.checked_at), (Origin::Cache, first.checked_at));
+
+        let retried = client.get_data_classes().with_retry(RetryPolicy::new(2)).send_checked();
+        assert_eq!(retried.unwrap().origin, Origin::Cache);
+
+        let client = Clientv2::with_backend("test-rust-client",
+                                            Arc::new(Canned(429, Some("3"), "")));
+        match client.get_breaches().send() {
+            Err(Error::RateLimited(Some(3), _)) => {}
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+
+    fn assert_transport_error_is_redacted(mut client: Clientv2) {
+        client.set_base_url("http://127.0.0.1:1/").unwrap();
+        let err = client.get_breaches_acct("secret@example.com").send().unwrap_err();
+        assert!(matches!(err, Error::Transport { .. }), "{:?}", err);
+
+        let mut source: Option<&dyn std::error::Error> = Some(&err);
+        while let Some(e) = source {
+            assert!(!e.to_string().contains("secret"), "{}", e);
+            source = e.source();
+        }
+        assert!(!format!("{:?}", err).contains("secret"));
+    }
+
+    #[test]
+    fn transport_errors_do_not_leak_accounts() {
+        #[cfg(feature = "reqwest")]
+        assert_transport_error_is_redacted(Clientv2::new("test-rust-client"));
+        #[cfg(feature = "ureq")]
+        {
+            let backend = Arc::new(crate::backend::UreqBackend::default());
+            assert_transport_error_is_redacted(Clientv2::with_backend("test-rust-client", backend));
+        }
+    }
+
+    #[test]
+    fn huge_retry_after_does_not_overflow() {
+        let client = Clientv2::with_backend("test-rust-client",
+                                            Arc::new(Canned(429, Some("18446744073709551615"), "")));
+        match client.get_breaches().send() {
+            Err(Error::RateLimited(Some(86400), _)) => {}
+            r => panic!("unexpected result: {:?}", r),
+        }
+        assert_eq!(client.quota().snapshot().remaining, Some(0));
+        client.quota().record_rate_limited(Some(Duration::MAX));
+    }
+
+    #[test]
+    fn errors_carry_debug_headers() {
+        let client = Clientv2::with_backend("test-rust-client", Arc::new(Canned(200, None, "{")));
+
+        let err = client.get_breaches().send().unwrap_err();
+        assert!(err.to_string().ends_with("[cf-ray: 8a1b2c3d4e5f6a7b-AMS]"), "{}", err);
+
+        let client = Clientv2::with_backend("test-rust-client", Arc::new(Canned(429, None, "")));
+        let err = client.get_breaches().send().unwrap_err();
+        assert!(matches!(err, Error::RateLimited(None, _)));
+        assert!(err.to_string().ends_with("[cf-ray: 8a1b2c3d4e5f6a7b-AMS]"), "{}", err);
+        assert_eq!(err.debug_headers()[0].1, "8a1b2c3d4e5f6a7b-AMS");
+
+        let client = Clientv2::with_backend("test-rust-client", Arc::new(Canned(200, None, "[]")));
+        let checked = client.get_pastes("test@example.com").send_checked().unwrap();
+        assert_eq!(checked.debug_headers,
+                   [("cf-ray".to_owned(), "8a1b2c3d4e5f6a7b-AMS".to_owned())]);
+    }
+
+    #[test]
+    fn schema_strictness_decides_on_unknown_fields() {
+        let backend = Arc::new(Canned(200, None, r#"[{"Name":"Adobe","IsMalware":false}]"#));
+        let mut client = Clientv2::with_backend("test-rust-client", backend);
+        client.set_schema_validation(true);
+        assert_eq!(client.get_breaches().send().unwrap()[0].name, "Adobe");
+
+        client.set_schema_strictness(Strictness::Strict);
+        match client.get_breaches().send() {
+            Err(Error::InvalidResponse { source, .. }) => {
+                assert!(matches!(*source, Error::SchemaMismatch { .. }), "{}", source)
+            }
+            other => panic!("expected a schema mismatch, got {:?}", other),
+        }
 
+        let backend = Arc::new(Canned(503, None, r#"{"statusCode":503,"message":"Down"}"#));
+        let mut client = Clientv2::with_backend("test-rust-client", backend);
+        client.set_schema_validation(true).set_schema_strictness(Strictness::Strict);
+        match client.get_breaches().send() {
+            Err(Error::InvalidResponse { source, .. }) => {
+                assert!(matches!(*source, Error::UnexpectedStatus(503)), "{}", source)
+            }
+            other => panic!("expected an unexpected status, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn base_url_overrides_the_api_root() {
         let mut client = Clientv2::new("test-rust-client");
+        assert!(client.set_base_url("ftp://gateway.internal/").is_err());
+        client.set_base_url("https://gateway.internal/hibp").unwrap();
+
+        assert_eq!(url_of(&client.get_breach("Adobe".parse().unwrap())),
+                   "https://gateway.internal/hibp/breach/Adobe");
+        assert_eq!(url_of(&client.get_breaches().set_domain("adobe.com")),
+                   "https://gateway.internal/hibp/breaches?domain=adobe.com");
+    }
+
+    #[test]
+    fn profiles_can_be_tweaked_after_selection() {
+        let mut client = Clientv2::new("test-rust-client");
+        client.apply_profile(Profile::BulkAudit).unwrap().set_max_in_flight_per_host(8);
+
+        assert_eq!(client.rate_limiter().unwrap().interval(), Duration::from_millis(1700));
+        assert_eq!(client.host_limiter().unwrap().max_in_flight(), 8);
+        assert_eq!(client.retry_policy().unwrap().max_attempts, 6);
+    }
+
+    #[test]
+    fn profiles_keep_settings_the_caller_chose() {
+        let backend = Arc::new(Canned(200, None, "[]"));
+        let mut client = Clientv2::with_backend("test-rust-client", backend);
+        client.set_rate_limiter(Arc::new(RateLimiter::new(Duration::from_secs(3))))
+            .set_max_in_flight_per_host(8)
+            .set_retry_policy(RetryPolicy::new(1));
+        client.apply_profile(Profile::BulkAudit).unwrap();
+
+        assert_eq!(client.rate_limiter().unwrap().interval(), Duration::from_secs(3));
+        assert_eq!(client.host_limiter().unwrap().max_in_flight(), 8);
+        assert_eq!(client.retry_policy().unwrap().max_attempts, 1);
+        assert!(client.get_pastes("test@example.com").send().unwrap().is_empty());
+    }
 
-        let r = client.get_breaches_acct("test@example.com")
+    #[cfg(feature = "reqwest")]
+    #[test]
+    fn profiles_set_the_connect_timeout() {
+        let mut client = Clientv2::new("test-rust-client");
+        client.apply_profile(Profile::Ci).unwrap();
+        assert_eq!(client.transport().connect_timeout(), Some(Duration::from_secs(10)));
+
+        let mut transport = TransportConfig::default();
+        transport.set_connect_timeout(Duration::from_secs(2));
+        let mut tuned = Clientv2::new("test-rust-client");
+        tuned.set_transport(transport).unwrap().apply_profile(Profile::Ci).unwrap();
+        assert_eq!(tuned.transport().connect_timeout(), Some(Duration::from_secs(2)));
+
+        let backend = Arc::new(Canned(200, None, ""));
+        let mut custom = Clientv2::with_backend("test-rust-client", backend);
+        custom.apply_profile(Profile::Ci).unwrap();
+        assert_eq!(custom.transport().connect_timeout(), None);
+    }
+
+    // Answers 503 to the first request and an empty list after that.
+    #[derive(Debug, Default)]
+    struct Recovering(AtomicUsize);
+
+    impl HttpBackend for Recovering {
+        fn get(&self, _request: &Request) -> std::result::Result<HttpResponse, BoxError> {
+            let status = if self.0.fetch_add(1, Ordering::SeqCst) == 0 { 503 } else { 200 };
+            Ok(HttpResponse {
+                status,
+                content_type: Some("application/json".to_owned()),
+                retry_after: None,
+                content_length: None,
+                debug_headers: vec![],
+                body: Box::new(Cursor::new("[]")),
+            })
+        }
+    }
+
+    #[test]
+    fn client_retry_policy_covers_every_request() {
+        let backend = Arc::new(Recovering::default());
+        let clock = Arc::new(MockClock::new());
+        let mut client = Clientv2::with_backend("test-rust-client", backend.clone());
+        client.set_retry_policy(RetryPolicy::new(2).set_clock(clock.clone()));
+
+        assert!(client.get_breaches().send().unwrap().is_empty());
+        assert_eq!(backend.0.load(Ordering::SeqCst), 2);
+        assert_eq!(clock.elapsed(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn with_retry_replaces_the_client_retry_policy() {
+        let backend = Arc::new(Recovering::default());
+        let clock = Arc::new(MockClock::new());
+        let mut client = Clientv2::with_backend("test-rust-client", backend.clone());
+        client.set_retry_policy(RetryPolicy::new(6).set_clock(clock.clone()));
+
+        assert!(client.get_breaches().with_retry(RetryPolicy::new(1)).send().is_err());
+        assert_eq!(backend.0.load(Ordering::SeqCst), 1);
+        assert_eq!(clock.elapsed(), Duration::ZERO);
+    }
+
+    // Talks to the live API, so it only runs on request: `cargo test -- --ignored`.
+    #[test]
+    #[ignore]
+    fn it_works() {
+        let client = Clientv2::new("test-rust-client");
+
+        client.get_breaches_acct("test@example.com")
             .send()
             .unwrap();
 
-        let r = client.get_breaches()
+        client.get_breaches()
             .send()
             .unwrap();
 
+        client.get_data_classes().send().unwrap();
 
-        let r = client.get_data_classes().send().unwrap();
-
-        let r = client.get_pastes("test@example.com").send().unwrap();
+        client.get_pastes("test@example.com").send().unwrap();
     }
 }
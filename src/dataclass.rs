@@ -0,0 +1,59 @@
+use std::convert::Infallible;
+use std::fmt;
+use std::str::FromStr;
+
+macro_rules! data_classes {
+    ($($variant:ident => $name:expr,)*) => {
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        #[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+        pub enum DataClass {
+            $($variant,)*
+            Other(String),
+        }
+
+        impl DataClass {
+            pub fn from_name(name: &str) -> DataClass {
+                match name {
+                    $($name => DataClass::$variant,)*
+                    other => DataClass::Other(other.to_owned()),
+                }
+            }
+
+            pub fn as_str(&self) -> &str {
+                match *self {
+                    $(DataClass::$variant => $name,)*
+                    DataClass::Other(ref name) => name,
+                }
+            }
+        }
+    }
+}
+
+include!(concat!(env!("OUT_DIR"), "/data_classes.rs"));
+
+impl FromStr for DataClass {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<DataClass, Infallible> {
+        Ok(DataClass::from_name(s))
+    }
+}
+
+impl fmt::Display for DataClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_names() {
+        assert_eq!(DataClass::from_name("Email addresses"), DataClass::EmailAddresses);
+        assert_eq!(DataClass::EmailAddresses.to_string(), "Email addresses");
+        assert_eq!(DataClass::from_name("Astrological signs"),
+                   DataClass::Other("Astrological signs".to_owned()));
+    }
+}
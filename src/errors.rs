@@ -1 +1,241 @@
-error_chain!{}
+use thiserror::Error;
+
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const SNIPPET_LEN: usize = 128;
+
+pub fn snippet(body: &str) -> String {
+    let mut out: String = body.chars()
+        .take(SNIPPET_LEN)
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+
+    if body.chars().nth(SNIPPET_LEN).is_some() {
+        out.push('\u{2026}');
+    }
+    out
+}
+
+// Rendered as " [cf-ray: ...]" so support identifiers survive into logged error messages.
+fn header_suffix(headers: &[(String, String)]) -> String {
+    headers.iter().map(|(name, value)| format!(" [{}: {}]", name, value)).collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResponseContext {
+    pub endpoint: &'static str,
+    pub url: String,
+    pub status: u16,
+    pub body_snippet: String,
+    pub debug_headers: Vec<(String, String)>,
+}
+
+impl ResponseContext {
+    pub fn new(endpoint: &'static str, url: String, status: u16, body: &str) -> ResponseContext {
+        ResponseContext {
+            endpoint,
+            url,
+            status,
+            body_snippet: snippet(body),
+            debug_headers: vec![],
+        }
+    }
+}
+
+impl fmt::Display for ResponseContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f,
+               "{} (HTTP {}) at {}: {:?}{}",
+               self.endpoint,
+               self.status,
+               self.url,
+               self.body_snippet,
+               header_suffix(&self.debug_headers))
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Invalid account {0:?}: {1}")]
+    InvalidAccount(String, &'static str),
+
+    #[error("Invalid breach name {0:?}: {1}")]
+    InvalidBreachName(String, &'static str),
+
+    #[error("Unknown profile {0:?}; expected interactive, bulk-audit or ci")]
+    UnknownProfile(String),
+
+    #[error("Invalid base URL {0:?}: {1}")]
+    InvalidBaseUrl(String, &'static str),
+
+    #[cfg(feature = "reqwest")]
+    #[error("Invalid proxy {0:?}: {1}")]
+    InvalidProxy(String, &'static str),
+
+    #[cfg(feature = "reqwest")]
+    #[error("Failed to build HTTP client")]
+    ClientBuild(#[source] reqwest::Error),
+
+    #[cfg(feature = "client")]
+    #[error("Failed to send request to {endpoint} at {url}")]
+    Transport {
+        endpoint: &'static str,
+        url: String,
+        #[source]
+        source: crate::backend::BoxError,
+    },
+
+    #[error("Failed to read response body")]
+    Read(#[source] io::Error),
+
+    #[error("Response body is not valid {encoding} after byte {valid_up_to}: {body_snippet:?}")]
+    InvalidEncoding {
+        encoding: &'static str,
+        valid_up_to: usize,
+        body_snippet: String,
+    },
+
+    #[error("Unexpected response from {context}")]
+    InvalidResponse {
+        context: ResponseContext,
+        #[source]
+        source: Box<Error>,
+    },
+
+    #[error("{message}")]
+    Parse {
+        message: String,
+        #[source]
+        source: Option<serde_json::Error>,
+    },
+
+    #[error("Response does not match the {endpoint} schema: {}",
+            .mismatches.iter().map(ToString::to_string).collect::<Vec<_>>().join("; "))]
+    SchemaMismatch {
+        endpoint: &'static str,
+        mismatches: Vec<crate::schema::Mismatch>,
+    },
+
+    #[error("Received a non-JSON page instead of data (HTTP {}); the service may be \
+             unavailable{}", .0, header_suffix(.1))]
+    ServiceUnavailable(u16, Vec<(String, String)>),
+
+    #[error("Received a browser challenge page instead of data (HTTP {}){}",
+            .0, header_suffix(.1))]
+    ChallengeRequired(u16, Vec<(String, String)>),
+
+    #[error("Unexpected HTTP status {0}")]
+    UnexpectedStatus(u16),
+
+    #[error("Rate limit exceeded{}{}",
+            .0.map_or(String::new(), |s| format!(", retry after {}s", s)), header_suffix(.1))]
+    RateLimited(Option<u64>, Vec<(String, String)>),
+
+    #[error("Request budget of {max_requests} per {}s exhausted, resets in {}s",
+            .window.as_secs(), .resets_in.as_secs())]
+    BudgetExhausted {
+        max_requests: u32,
+        window: Duration,
+        resets_in: Duration,
+    },
+
+    #[error("Response body exceeded the configured limit of {0} bytes")]
+    ResponseTooLarge(u64),
+
+    #[error("Failed to access checkpoint {}", .0.display())]
+    Checkpoint(PathBuf, #[source] io::Error),
+
+    #[error("Checkpoint {} was written with a different pseudonym key; configure the same key \
+             with set_pseudonym_key to resume it", .0.display())]
+    PseudonymKeyMismatch(PathBuf),
+
+    #[error("Failed to write audit trail {}", .0.display())]
+    AuditTrail(PathBuf, #[source] io::Error),
+
+    #[cfg(feature = "arrow")]
+    #[error("Failed to export to Arrow")]
+    Arrow(#[from] arrow::error::ArrowError),
+
+    #[cfg(feature = "arrow")]
+    #[error("Failed to write Parquet")]
+    Parquet(#[from] parquet::errors::ParquetError),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub(crate) fn parse<S: Into<String>>(message: S) -> Error {
+        Error::Parse {
+            message: message.into(),
+            source: None,
+        }
+    }
+
+    #[cfg(feature = "client")]
+    pub(crate) fn with_debug_headers(self, headers: &[(String, String)]) -> Error {
+        match self {
+            Error::ServiceUnavailable(status, _) => {
+                Error::ServiceUnavailable(status, headers.into())
+            }
+            Error::ChallengeRequired(status, _) => Error::ChallengeRequired(status, headers.into()),
+            Error::RateLimited(secs, _) => Error::RateLimited(secs, headers.into()),
+            e => e,
+        }
+    }
+
+    pub fn debug_headers(&self) -> &[(String, String)] {
+        match *self {
+            Error::ServiceUnavailable(_, ref headers) |
+            Error::ChallengeRequired(_, ref headers) |
+            Error::RateLimited(_, ref headers) => headers,
+            Error::InvalidResponse { ref context, .. } => &context.debug_headers,
+            _ => &[],
+        }
+    }
+
+    pub(crate) fn json(message: &str, source: serde_json::Error) -> Error {
+        Error::Parse {
+            message: message.to_owned(),
+            source: Some(source),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::breaches_from_str;
+
+    use std::error::Error as StdError;
+
+    #[test]
+    fn snippets_are_truncated_and_single_line() {
+        let body = format!("<html>\n{}</html>", "x".repeat(500));
+        let s = snippet(&body);
+
+        assert!(s.starts_with("<html> x"));
+        assert!(s.ends_with('\u{2026}'));
+        assert_eq!(s.chars().count(), SNIPPET_LEN + 1);
+    }
+
+    #[test]
+    fn parse_errors_keep_their_source() {
+        let err = breaches_from_str("{").unwrap_err();
+
+        let source = err.source().expect("missing source");
+        assert!(source.downcast_ref::<serde_json::Error>().is_some());
+    }
+
+    #[test]
+    #[cfg(feature = "client")]
+    fn unavailable_pages_keep_debug_headers() {
+        let headers = [("cf-ray".to_owned(), "8a1b2c3d4e5f6a7b-AMS".to_owned())];
+        let err = Error::ServiceUnavailable(502, vec![]).with_debug_headers(&headers);
+
+        assert_eq!(err.debug_headers(), headers);
+        assert!(err.to_string().ends_with(" [cf-ray: 8a1b2c3d4e5f6a7b-AMS]"), "{}", err);
+    }
+}
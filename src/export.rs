@@ -0,0 +1,98 @@
+use crate::errors::*;
+use crate::model::Breach;
+
+use arrow::array::{ArrayRef, BooleanArray, ListBuilder, StringArray, StringBuilder, UInt64Array};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::ArrowWriter;
+
+use std::io::Write;
+use std::sync::Arc;
+
+pub fn breaches_schema() -> Schema {
+    let data_class = Field::new("item", DataType::Utf8, true);
+
+    Schema::new(vec![Field::new("name", DataType::Utf8, false),
+                     Field::new("title", DataType::Utf8, true),
+                     Field::new("domain", DataType::Utf8, true),
+                     Field::new("breach_date", DataType::Utf8, true),
+                     Field::new("added_date", DataType::Utf8, true),
+                     Field::new("pwn_count", DataType::UInt64, true),
+                     Field::new("description", DataType::Utf8, true),
+                     Field::new("data_classes", DataType::List(Arc::new(data_class)), true),
+                     Field::new("is_verified", DataType::Boolean, true),
+                     Field::new("is_sensitive", DataType::Boolean, true),
+                     Field::new("is_retired", DataType::Boolean, true)])
+}
+
+fn strings<'a, F>(breaches: &'a [Breach], f: F) -> ArrayRef
+    where F: Fn(&'a Breach) -> Option<&'a str>
+{
+    Arc::new(breaches.iter().map(f).collect::<StringArray>())
+}
+
+fn bools<F>(breaches: &[Breach], f: F) -> ArrayRef
+    where F: Fn(&Breach) -> Option<bool>
+{
+    Arc::new(breaches.iter().map(f).collect::<BooleanArray>())
+}
+
+pub fn breaches_to_record_batch(breaches: &[Breach]) -> Result<RecordBatch> {
+    let mut data_classes = ListBuilder::new(StringBuilder::new());
+    for breach in breaches {
+        match breach.data_classes {
+            Some(ref dc) => {
+                for class in dc {
+                    data_classes.values().append_value(class);
+                }
+                data_classes.append(true);
+            }
+            None => data_classes.append(false),
+        }
+    }
+
+    let columns: Vec<ArrayRef> =
+        vec![strings(breaches, |b| Some(b.name.as_str())),
+             strings(breaches, |b| b.title.as_deref()),
+             strings(breaches, |b| b.domain.as_deref()),
+             strings(breaches, |b| b.breach_date.as_deref()),
+             strings(breaches, |b| b.added_date.as_deref()),
+             Arc::new(breaches.iter().map(|b| b.pwn_count).collect::<UInt64Array>()),
+             strings(breaches, |b| b.description.as_deref()),
+             Arc::new(data_classes.finish()),
+             bools(breaches, |b| b.is_verified),
+             bools(breaches, |b| b.is_sensitive),
+             bools(breaches, |b| b.is_retired)];
+
+    Ok(RecordBatch::try_new(Arc::new(breaches_schema()), columns)?)
+}
+
+pub fn write_breaches_parquet<W: Write + Send>(breaches: &[Breach], writer: W) -> Result<()> {
+    let batch = breaches_to_record_batch(breaches)?;
+
+    let mut writer = ArrowWriter::try_new(writer, batch.schema(), None)?;
+    writer.write(&batch)?;
+    writer.close()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::breaches_from_str;
+
+    #[test]
+    fn builds_one_row_per_breach() {
+        let breaches = breaches_from_str(r#"[{"Name":"Adobe","DataClasses":["Passwords"]},
+                                             {"Name":"Yahoo","PwnCount":3}]"#)
+            .unwrap();
+
+        let batch = breaches_to_record_batch(&breaches).unwrap();
+        assert_eq!(batch.num_rows(), 2);
+
+        let mut out = vec![];
+        write_breaches_parquet(&breaches, &mut out).unwrap();
+        assert_eq!(&out[..4], b"PAR1");
+    }
+}
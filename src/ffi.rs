@@ -0,0 +1,134 @@
+use crate::clientv2::Clientv2;
+
+use serde_json::Value;
+
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+
+pub const HIBP_OK: c_int = 0;
+pub const HIBP_INVALID_ARGUMENT: c_int = -1;
+pub const HIBP_REQUEST_FAILED: c_int = -2;
+
+pub struct HibpClient {
+    client: Clientv2<'static>,
+}
+
+unsafe fn to_str<'a>(s: *const c_char) -> Option<&'a str> {
+    if s.is_null() {
+        None
+    } else {
+        CStr::from_ptr(s).to_str().ok()
+    }
+}
+
+unsafe fn write_out(out: *mut *mut c_char, s: String) {
+    if !out.is_null() {
+        *out = CString::new(s).map(CString::into_raw).unwrap_or(ptr::null_mut());
+    }
+}
+
+/// Creates a client that identifies itself with `user_agent`.
+///
+/// Returns null if `user_agent` is null or not UTF-8.
+///
+/// # Safety
+///
+/// `user_agent` must be null or point to a NUL-terminated string. The
+/// returned client must be released with `hibp_client_free`.
+#[no_mangle]
+pub unsafe extern "C" fn hibp_client_new(user_agent: *const c_char) -> *mut HibpClient {
+    match to_str(user_agent) {
+        Some(ua) => Box::into_raw(Box::new(HibpClient { client: Clientv2::new(ua.to_owned()) })),
+        None => ptr::null_mut(),
+    }
+}
+
+/// Releases a client created by `hibp_client_new`.
+///
+/// # Safety
+///
+/// `client` must be null or a pointer returned by `hibp_client_new` that has
+/// not already been freed.
+#[no_mangle]
+pub unsafe extern "C" fn hibp_client_free(client: *mut HibpClient) {
+    if !client.is_null() {
+        drop(Box::from_raw(client));
+    }
+}
+
+/// Looks up the breaches `account` appears in.
+///
+/// On `HIBP_OK`, `*out` holds a JSON array of breach names. On
+/// `HIBP_REQUEST_FAILED`, `*out` holds the error message. Either string must
+/// be released with `hibp_string_free`.
+///
+/// # Safety
+///
+/// `client` must come from `hibp_client_new`, `account` must point to a
+/// NUL-terminated string and `out` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn hibp_check_account(client: *const HibpClient,
+                                            account: *const c_char,
+                                            out: *mut *mut c_char)
+                                            -> c_int {
+    let account = match (client.as_ref(), to_str(account)) {
+        (Some(client), Some(account)) => client.client.get_breaches_acct(account),
+        _ => return HIBP_INVALID_ARGUMENT,
+    };
+
+    match account.truncated().send() {
+        Ok(names) => {
+            let names = names.iter().map(|n| Value::String(n.to_string())).collect();
+            write_out(out, Value::Array(names).to_string());
+            HIBP_OK
+        }
+        Err(e) => {
+            write_out(out, e.to_string());
+            HIBP_REQUEST_FAILED
+        }
+    }
+}
+
+/// Releases a string returned through an `out` parameter.
+///
+/// # Safety
+///
+/// `s` must be null or a string handed out by this library that has not
+/// already been freed.
+#[no_mangle]
+pub unsafe extern "C" fn hibp_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_null_arguments() {
+        unsafe {
+            assert!(hibp_client_new(ptr::null()).is_null());
+            assert_eq!(hibp_check_account(ptr::null(), c"a@example.com".as_ptr(), ptr::null_mut()),
+                       HIBP_INVALID_ARGUMENT);
+        }
+    }
+
+    #[test]
+    fn reports_errors_as_strings() {
+        unsafe {
+            let client = hibp_client_new(c"test-rust-client".as_ptr());
+            assert!(!client.is_null());
+
+            let mut out = ptr::null_mut();
+            assert_eq!(hibp_check_account(client, c" ".as_ptr(), &mut out),
+                       HIBP_REQUEST_FAILED);
+            assert!(CStr::from_ptr(out).to_str().unwrap().contains("account is empty"));
+
+            hibp_string_free(out);
+            hibp_client_free(client);
+        }
+    }
+}
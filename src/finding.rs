@@ -0,0 +1,138 @@
+use crate::model::{Breach, DataClass, Paste};
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Finding {
+    Breach { subject: String, breach: Breach },
+    Paste { subject: String, paste: Paste },
+}
+
+fn is_high_risk(class: &DataClass) -> bool {
+    matches!(*class,
+             DataClass::Passwords | DataClass::HistoricalPasswords | DataClass::CreditCards |
+             DataClass::BankAccountNumbers | DataClass::AuthTokens |
+             DataClass::SocialSecurityNumbers | DataClass::GovernmentIssuedIds |
+             DataClass::PassportNumbers | DataClass::SecurityQuestionsAndAnswers |
+             DataClass::BiometricData)
+}
+
+fn is_elevated_risk(class: &DataClass) -> bool {
+    matches!(*class,
+             DataClass::PartialCreditCardData | DataClass::PasswordHints |
+             DataClass::HealthInsuranceInformation | DataClass::PrivateMessages)
+}
+
+pub fn breach_severity(breach: &Breach) -> Severity {
+    let classes: Vec<DataClass> = breach.data_classes
+        .iter()
+        .flatten()
+        .map(|c| DataClass::from_name(c))
+        .collect();
+
+    if classes.iter().any(is_high_risk) {
+        Severity::High
+    } else if breach.is_sensitive == Some(true) || classes.iter().any(is_elevated_risk) {
+        Severity::Medium
+    } else {
+        Severity::Low
+    }
+}
+
+impl Finding {
+    pub fn subject(&self) -> &str {
+        match *self {
+            Finding::Breach { ref subject, .. } | Finding::Paste { ref subject, .. } => subject,
+        }
+    }
+
+    pub fn severity(&self) -> Severity {
+        match *self {
+            Finding::Breach { ref breach, .. } => breach_severity(breach),
+            Finding::Paste { .. } => Severity::Medium,
+        }
+    }
+
+    pub fn date(&self) -> Option<&str> {
+        match *self {
+            Finding::Breach { ref breach, .. } => breach.breach_date.as_deref(),
+            Finding::Paste { ref paste, .. } => paste.date.as_deref(),
+        }
+    }
+
+    pub fn title(&self) -> &str {
+        match *self {
+            Finding::Breach { ref breach, .. } => breach.title.as_deref().unwrap_or(&breach.name),
+            Finding::Paste { ref paste, .. } => paste.title.as_deref().unwrap_or(&paste.id),
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match *self {
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+        })
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match *self {
+            Finding::Breach { .. } => "breach",
+            Finding::Paste { .. } => "paste",
+        };
+        write!(f, "[{}] {} {}: {}", self.severity(), kind, self.subject(), self.title())?;
+        if let Some(date) = self.date() {
+            write!(f, " ({})", date)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{breach_from_str, pastes_from_str};
+
+    #[test]
+    fn grades_breaches_by_data_class() {
+        let adobe = breach_from_str(r#"{"Name":"Adobe","BreachDate":"2013-10-04",
+                                        "DataClasses":["Email addresses","Passwords"]}"#)
+            .unwrap();
+        let forum = breach_from_str(r#"{"Name":"Forum","DataClasses":["Usernames"]}"#).unwrap();
+
+        let finding = Finding::Breach {
+            subject: "test@example.com".to_owned(),
+            breach: adobe,
+        };
+        assert_eq!(finding.severity(), Severity::High);
+        assert_eq!(finding.to_string(), "[high] breach test@example.com: Adobe (2013-10-04)");
+        assert_eq!(breach_severity(&forum), Severity::Low);
+    }
+
+    #[test]
+    fn pastes_share_the_interface() {
+        let paste = pastes_from_str(r#"[{"Source":"Pastebin","Id":"8Q0BvKD8","Title":null,
+                                         "Date":null,"EmailCount":1}]"#)
+            .unwrap()
+            .remove(0);
+        let finding = Finding::Paste {
+            subject: "test@example.com".to_owned(),
+            paste,
+        };
+
+        assert_eq!(finding.subject(), "test@example.com");
+        assert_eq!(finding.title(), "8Q0BvKD8");
+        assert_eq!(finding.date(), None);
+    }
+}
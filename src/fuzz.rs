@@ -0,0 +1,179 @@
+use crate::model::{Breach, Paste};
+
+use proptest::collection::vec;
+use proptest::option;
+use proptest::prelude::*;
+use serde_json::{Map, Value};
+
+pub fn breach() -> BoxedStrategy<Breach> {
+    let text = || option::of(any::<String>());
+
+    (any::<String>(),
+     text(),
+     text(),
+     text(),
+     text(),
+     option::of(any::<u64>()),
+     text(),
+     option::of(vec(any::<String>(), 0..8)),
+     option::of(any::<bool>()),
+     option::of(any::<bool>()),
+     option::of(any::<bool>()))
+        .prop_map(|(name,
+                    title,
+                    domain,
+                    breach_date,
+                    added_date,
+                    pwn_count,
+                    description,
+                    data_classes,
+                    is_verified,
+                    is_sensitive,
+                    is_retired)| {
+            Breach {
+                name,
+                title,
+                domain,
+                breach_date,
+                added_date,
+                pwn_count,
+                description,
+                data_classes,
+                is_verified,
+                is_sensitive,
+                is_retired,
+            }
+        })
+        .boxed()
+}
+
+pub fn paste() -> BoxedStrategy<Paste> {
+    (any::<String>(),
+     any::<String>(),
+     option::of(any::<String>()),
+     option::of(any::<String>()),
+     any::<u64>())
+        .prop_map(|(source, id, title, date, email_count)| {
+            Paste {
+                source,
+                id,
+                title,
+                date,
+                email_count,
+            }
+        })
+        .boxed()
+}
+
+fn insert_string(obj: &mut Map<String, Value>, key: &str, value: &Option<String>) {
+    if let Some(ref v) = *value {
+        obj.insert(key.to_owned(), Value::String(v.clone()));
+    }
+}
+
+fn insert_bool(obj: &mut Map<String, Value>, key: &str, value: Option<bool>) {
+    if let Some(v) = value {
+        obj.insert(key.to_owned(), Value::Bool(v));
+    }
+}
+
+pub fn breach_to_value(breach: &Breach) -> Value {
+    let mut obj = Map::new();
+
+    obj.insert("Name".to_owned(), Value::String(breach.name.clone()));
+    insert_string(&mut obj, "Title", &breach.title);
+    insert_string(&mut obj, "Domain", &breach.domain);
+    insert_string(&mut obj, "BreachDate", &breach.breach_date);
+    insert_string(&mut obj, "AddedDate", &breach.added_date);
+    if let Some(c) = breach.pwn_count {
+        obj.insert("PwnCount".to_owned(), Value::from(c));
+    }
+    insert_string(&mut obj, "Description", &breach.description);
+    if let Some(ref dc) = breach.data_classes {
+        obj.insert("DataClasses".to_owned(),
+                   Value::Array(dc.iter().cloned().map(Value::String).collect()));
+    }
+    insert_bool(&mut obj, "IsVerified", breach.is_verified);
+    insert_bool(&mut obj, "IsSensitive", breach.is_sensitive);
+    insert_bool(&mut obj, "IsRetired", breach.is_retired);
+
+    Value::Object(obj)
+}
+
+pub fn paste_to_value(paste: &Paste) -> Value {
+    let nullable = |v: &Option<String>| v.clone().map_or(Value::Null, Value::String);
+    let mut obj = Map::new();
+
+    obj.insert("Source".to_owned(), Value::String(paste.source.clone()));
+    obj.insert("Id".to_owned(), Value::String(paste.id.clone()));
+    obj.insert("Title".to_owned(), nullable(&paste.title));
+    obj.insert("Date".to_owned(), nullable(&paste.date));
+    obj.insert("EmailCount".to_owned(), Value::from(paste.email_count));
+
+    Value::Object(obj)
+}
+
+pub fn breaches_body() -> BoxedStrategy<(Vec<Breach>, String)> {
+    vec(breach(), 0..16)
+        .prop_map(|breaches| {
+            let body = Value::Array(breaches.iter().map(breach_to_value).collect());
+            (breaches, serde_json::to_string(&body).unwrap())
+        })
+        .boxed()
+}
+
+pub fn pastes_body() -> BoxedStrategy<(Vec<Paste>, String)> {
+    vec(paste(), 0..16)
+        .prop_map(|pastes| {
+            let body = Value::Array(pastes.iter().map(paste_to_value).collect());
+            (pastes, serde_json::to_string(&body).unwrap())
+        })
+        .boxed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{breaches_from_str, pastes_from_str};
+    use crate::protocol::{self, AccountQuery};
+
+    proptest! {
+        #[test]
+        fn breaches_round_trip((breaches, body) in breaches_body()) {
+            let parsed = breaches_from_str(&body).unwrap();
+            prop_assert_eq!(format!("{:?}", parsed), format!("{:?}", breaches));
+        }
+
+        #[test]
+        fn pastes_round_trip((pastes, body) in pastes_body()) {
+            let parsed = pastes_from_str(&body).unwrap();
+            prop_assert_eq!(format!("{:?}", parsed), format!("{:?}", pastes));
+        }
+
+        #[test]
+        fn breach_parser_never_panics(body in ".*") {
+            let _ = breaches_from_str(&body);
+        }
+
+        #[test]
+        fn account_urls_keep_their_shape(account in any::<String>()) {
+            prop_assume!(protocol::validate_account(&account).is_ok());
+
+            let url = protocol::breached_account_url(&account, AccountQuery::default());
+            let segments: Vec<_> = url.path_segments().unwrap().collect();
+            prop_assert_eq!(&segments[..3], &["api", "v2", "breachedaccount"]);
+            prop_assert_eq!(segments.len(), 4);
+            prop_assert_eq!(url.query(), None);
+            prop_assert_eq!(url.fragment(), None);
+        }
+
+        #[test]
+        fn paste_urls_keep_their_shape(account in any::<String>()) {
+            prop_assume!(protocol::validate_account(&account).is_ok());
+
+            let url = protocol::paste_account_url(&account);
+            prop_assert_eq!(url.path_segments().unwrap().count(), 4);
+            prop_assert_eq!(url.query(), None);
+        }
+    }
+}
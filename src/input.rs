@@ -0,0 +1,150 @@
+use crate::errors::*;
+use crate::protocol;
+
+use std::collections::HashSet;
+use std::io::BufRead;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkipReason {
+    Invalid(&'static str),
+    Duplicate,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Skipped {
+    pub line: usize,
+    pub content: String,
+    pub reason: SkipReason,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Ingested {
+    pub accounts: Vec<String>,
+    pub skipped: Vec<Skipped>,
+}
+
+fn unquote(field: &str) -> &str {
+    let field = field.trim();
+    field.strip_prefix('"')
+        .and_then(|f| f.strip_suffix('"'))
+        .unwrap_or(field)
+        .trim()
+}
+
+pub fn normalize_email(email: &str) -> std::result::Result<String, &'static str> {
+    let email = unquote(email).to_lowercase();
+
+    let (local, domain) = email.split_once('@').ok_or("missing @")?;
+    if local.is_empty() || domain.is_empty() || domain.contains('@') {
+        return Err("not of the form local@domain");
+    }
+    if !domain.contains('.') || domain.starts_with('.') || domain.ends_with('.') {
+        return Err("domain is not fully qualified");
+    }
+    if email.chars().any(char::is_whitespace) {
+        return Err("contains whitespace");
+    }
+
+    match protocol::validate_account(&email) {
+        Ok(()) => Ok(email),
+        Err(Error::InvalidAccount(_, reason)) => Err(reason),
+        Err(_) => Err("invalid account"),
+    }
+}
+
+pub fn read_accounts<R: BufRead>(reader: R) -> Result<Ingested> {
+    let mut ingested = Ingested::default();
+    let mut seen = HashSet::new();
+
+    // Split on raw bytes so one badly encoded line is skipped instead of ending the import.
+    for (i, line) in reader.split(b'\n').enumerate() {
+        let mut line = line.map_err(Error::Read)?;
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        let line = match String::from_utf8(line) {
+            Ok(line) => line,
+            Err(e) => {
+                ingested.skipped.push(Skipped {
+                    line: i + 1,
+                    content: String::from_utf8_lossy(e.as_bytes()).into_owned(),
+                    reason: SkipReason::Invalid("not valid UTF-8"),
+                });
+                continue;
+            }
+        };
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+
+        let field = trimmed.split(',').find(|f| f.contains('@')).unwrap_or(trimmed);
+        let reason = match normalize_email(field) {
+            Ok(email) => {
+                if seen.insert(email.clone()) {
+                    ingested.accounts.push(email);
+                    continue;
+                }
+                SkipReason::Duplicate
+            }
+            Err(reason) => SkipReason::Invalid(reason),
+        };
+
+        ingested.skipped.push(Skipped {
+            line: i + 1,
+            content: line,
+            reason,
+        });
+    }
+
+    Ok(ingested)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_newline_delimited_emails() {
+        let input = "Test@Example.com\n\n# comment\n test@example.com \nnot-an-email\n";
+        let ingested = read_accounts(input.as_bytes()).unwrap();
+
+        assert_eq!(ingested.accounts, vec!["test@example.com"]);
+        assert_eq!(ingested.skipped,
+                   vec![Skipped {
+                            line: 4,
+                            content: " test@example.com ".to_owned(),
+                            reason: SkipReason::Duplicate,
+                        },
+                        Skipped {
+                            line: 5,
+                            content: "not-an-email".to_owned(),
+                            reason: SkipReason::Invalid("missing @"),
+                        }]);
+    }
+
+    #[test]
+    fn skips_lines_that_are_not_utf8() {
+        let input = b"a@example.com\r\nb\xff@example.com\nc@example.com";
+        let ingested = read_accounts(&input[..]).unwrap();
+
+        assert_eq!(ingested.accounts, vec!["a@example.com", "c@example.com"]);
+        assert_eq!(ingested.skipped,
+                   vec![Skipped {
+                            line: 2,
+                            content: "b\u{fffd}@example.com".to_owned(),
+                            reason: SkipReason::Invalid("not valid UTF-8"),
+                        }]);
+    }
+
+    #[test]
+    fn picks_the_email_column_from_csv() {
+        let input = "name,email\nAlice,\"alice@example.com\"\nBob,bob@localhost\n";
+        let ingested = read_accounts(input.as_bytes()).unwrap();
+
+        assert_eq!(ingested.accounts, vec!["alice@example.com"]);
+        assert_eq!(ingested.skipped.len(), 2);
+        assert_eq!(ingested.skipped[1].reason,
+                   SkipReason::Invalid("domain is not fully qualified"));
+    }
+}
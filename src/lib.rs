@@ -1,12 +1,35 @@
-#![recursion_limit = "1024"]
-#[macro_use]
-extern crate error_chain;
-
-extern crate hyper;
-extern crate hyper_rustls;
-extern crate serde;
-extern crate serde_json;
-extern crate url;
-
 pub mod errors;
+pub mod clock;
+pub mod dataclass;
+pub mod model;
+pub mod finding;
+pub mod catalogue;
+pub mod protocol;
+pub mod schema;
+pub mod input;
+pub mod quota;
+pub mod redact;
+pub mod ratelimit;
+pub mod request;
+pub mod profile;
+pub mod trail;
+#[cfg(feature = "client")]
+pub mod backend;
+#[cfg(feature = "reqwest")]
+pub mod transport;
+#[cfg(feature = "client")]
 pub mod clientv2;
+#[cfg(feature = "client")]
+pub mod pool;
+#[cfg(feature = "client")]
+pub mod audit;
+#[cfg(feature = "client")]
+pub mod monitor;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "python")]
+mod python;
+#[cfg(feature = "fuzz")]
+pub mod fuzz;
+#[cfg(feature = "arrow")]
+pub mod export;
@@ -0,0 +1,517 @@
+use crate::catalogue::Catalogue;
+use crate::errors::*;
+
+pub use crate::dataclass::DataClass;
+
+use serde::Deserialize;
+use serde_json::{from_str, Map, Value};
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "python", pyo3::pyclass(module = "haveibeenpwnd", get_all, frozen, skip_from_py_object))]
+pub struct Breach {
+    pub name: String,
+    pub title: Option<String>,
+    pub domain: Option<String>,
+    pub breach_date: Option<String>,
+    pub added_date: Option<String>,
+    pub pwn_count: Option<u64>,
+    pub description: Option<String>,
+    pub data_classes: Option<Vec<String>>,
+    pub is_verified: Option<bool>,
+    pub is_sensitive: Option<bool>,
+    pub is_retired: Option<bool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BreachName(String);
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "fuzz", derive(arbitrary::Arbitrary))]
+#[cfg_attr(feature = "python", pyo3::pyclass(module = "haveibeenpwnd", get_all, frozen, skip_from_py_object))]
+pub struct Paste {
+    pub source: String,
+    pub id: String,
+    pub title: Option<String>,
+    pub date: Option<String>,
+    pub email_count: u64,
+}
+
+fn is_forbidden_in_name(c: char) -> bool {
+    c.is_control() || ['/', '\\', '?', '#', '%'].contains(&c)
+}
+
+impl BreachName {
+    pub fn new<S: Into<String>>(name: S) -> Result<BreachName> {
+        let name = name.into();
+        let invalid = |reason| Err(Error::InvalidBreachName(name.clone(), reason));
+
+        if name.trim().is_empty() {
+            invalid("name is empty")
+        } else if name == "." || name == ".." {
+            invalid("name is a relative path")
+        } else if name.chars().any(is_forbidden_in_name) {
+            invalid("name contains path or control characters")
+        } else {
+            Ok(BreachName(name))
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl FromStr for BreachName {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<BreachName> {
+        BreachName::new(s)
+    }
+}
+
+impl AsRef<str> for BreachName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(feature = "fuzz")]
+impl<'a> arbitrary::Arbitrary<'a> for BreachName {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<BreachName> {
+        let name: String = u.arbitrary::<String>()?
+            .chars()
+            .filter(|&c| !is_forbidden_in_name(c))
+            .collect();
+
+        Ok(BreachName::new(name).unwrap_or_else(|_| BreachName("Breach".to_owned())))
+    }
+}
+
+impl fmt::Display for BreachName {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl PartialEq for Breach {
+    fn eq(&self, other: &Breach) -> bool {
+        self.name == other.name
+    }
+}
+
+impl Eq for Breach {}
+
+impl Hash for Breach {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state)
+    }
+}
+
+impl PartialOrd for Breach {
+    fn partial_cmp(&self, other: &Breach) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Breach {
+    fn cmp(&self, other: &Breach) -> Ordering {
+        self.name.cmp(&other.name)
+    }
+}
+
+impl PartialEq for Paste {
+    fn eq(&self, other: &Paste) -> bool {
+        self.source == other.source && self.id == other.id
+    }
+}
+
+impl Eq for Paste {}
+
+impl Hash for Paste {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.source.hash(state);
+        self.id.hash(state);
+    }
+}
+
+impl PartialOrd for Paste {
+    fn partial_cmp(&self, other: &Paste) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Paste {
+    fn cmp(&self, other: &Paste) -> Ordering {
+        (&self.source, &self.id).cmp(&(&other.source, &other.id))
+    }
+}
+
+fn group_digits(n: u64) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(c);
+    }
+    out
+}
+
+impl fmt::Display for Breach {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.title.as_deref().unwrap_or(&self.name))?;
+
+        if let Some(domain) = self.domain.as_deref().filter(|d| !d.is_empty()) {
+            write!(f, " ({})", domain)?;
+        }
+
+        let mut details = vec![];
+        if let Some(count) = self.pwn_count {
+            details.push(format!("{} accounts", group_digits(count)));
+        }
+        if let Some(ref date) = self.breach_date {
+            details.push(format!("breached {}", date));
+        }
+        if !details.is_empty() {
+            write!(f, " \u{2014} {}", details.join(", "))?;
+        }
+
+        match self.data_classes {
+            Some(ref dc) if !dc.is_empty() => write!(f, ": {}", dc.join(", ")),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for Paste {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.source, self.id)?;
+
+        if let Some(title) = self.title.as_deref().filter(|t| !t.is_empty()) {
+            write!(f, " \"{}\"", title)?;
+        }
+
+        write!(f, " \u{2014} {} emails", group_digits(self.email_count))?;
+
+        match self.date {
+            Some(ref date) => write!(f, ", posted {}", date),
+            None => Ok(()),
+        }
+    }
+}
+
+fn get_serde_string(obj: &Value) -> Result<String> {
+    match obj.as_str() {
+        Some(s) => Ok(s.to_owned()),
+        None => Err(Error::parse(format!("Failed to parse value to string: {}", snippet(&obj.to_string())))),
+    }
+}
+
+fn get_serde_array(obj: &Value) -> Result<Vec<Value>> {
+    match obj.as_array() {
+        Some(s) => Ok(s.to_owned()),
+        None => Err(Error::parse(format!("Failed to parse value to array: {}", snippet(&obj.to_string())))),
+    }
+}
+
+fn get_serde_u64(obj: &Value) -> Result<u64> {
+    match obj.as_u64() {
+        Some(s) => Ok(s),
+        None => Err(Error::parse(format!("Failed to parse value to u64: {}", snippet(&obj.to_string())))),
+    }
+}
+
+fn get_serde_bool(obj: &Value) -> Result<bool> {
+    match obj.as_bool() {
+        Some(s) => Ok(s),
+        None => Err(Error::parse(format!("Failed to parse value to bool: {}", snippet(&obj.to_string())))),
+    }
+}
+
+fn get_or_err<'a>(name: &str, obj: &'a Map<String, Value>) -> Result<&'a Value> {
+    match obj.get(name) {
+        Some(n) => Ok(n),
+        None => Err(Error::parse(format!("Failed to get field: {:?}", name))),
+    }
+}
+
+fn parse_breach(obj: &Map<String, Value>) -> Result<Breach> {
+    Ok(Breach {
+        name: get_serde_string(get_or_err("Name", obj)?)?,
+        title: obj.get("Title").map(get_serde_string).map_or(Ok(None), |t| t.map(Some))?,
+        domain: obj.get("Domain")
+            .map(get_serde_string)
+            .map_or(Ok(None), |t| t.map(Some))?,
+        breach_date: obj.get("BreachDate")
+            .map(get_serde_string)
+            .map_or(Ok(None), |t| t.map(Some))?,
+        added_date: obj.get("AddedDate")
+            .map(get_serde_string)
+            .map_or(Ok(None), |t| t.map(Some))?,
+        pwn_count: obj.get("PwnCount")
+            .map(get_serde_u64)
+            .map_or(Ok(None), |t| t.map(Some))?,
+        description: obj.get("Description")
+            .map(get_serde_string)
+            .map_or(Ok(None), |t| t.map(Some))?,
+        data_classes: obj.get("DataClasses")
+            .map(|dc| {
+                let v = get_serde_array(dc)?;
+                v.iter()
+                    .map(get_serde_string)
+                    .collect::<Result<Vec<_>>>()
+            })
+            .map_or(Ok(None), |t| t.map(Some))?,
+        is_verified: obj.get("IsVerified")
+            .map(get_serde_bool)
+            .map_or(Ok(None), |t| t.map(Some))?,
+        is_sensitive: obj.get("IsSensitive")
+            .map(get_serde_bool)
+            .map_or(Ok(None), |t| t.map(Some))?,
+        is_retired: obj.get("IsRetired")
+            .map(get_serde_bool)
+            .map_or(Ok(None), |t| t.map(Some))?,
+    })
+}
+
+pub fn breaches_from_str(s: &str) -> Result<Vec<Breach>> {
+    let data: Value = from_str(s).map_err(|e| Error::json("Failed to parse breaches", e))?;
+
+    if let Some(data) = data.as_array() {
+        data.iter()
+            .map(|d| d.as_object())
+            .collect::<Option<Vec<_>>>()
+            .map_or(Err(Error::parse(format!("Failed to convert internal object from \
+                                              response: {}",
+                                             snippet(s)))),
+                    |o| {
+                        o.into_iter()
+                            .map(parse_breach)
+                            .collect::<Result<Vec<_>>>()
+                    })
+    } else if let Some(data) = data.as_object() {
+        vec![parse_breach(data)].into_iter().collect()
+    } else {
+        Err(Error::parse(format!("Improperly formatted response: {}", snippet(s))))
+    }
+}
+
+#[derive(Deserialize)]
+struct TruncatedBreach<'a> {
+    #[serde(rename = "Name", borrow)]
+    name: Cow<'a, str>,
+}
+
+pub fn breach_names_from_str(s: &str) -> Result<Vec<BreachName>> {
+    // Fast path: borrow names straight from the body, skipping the intermediate Value tree.
+    // Malformed bodies fall through to the slow path, which produces the detailed errors.
+    if let Ok(truncated) = from_str::<Vec<TruncatedBreach>>(s) {
+        return truncated.into_iter().map(|b| BreachName::new(b.name)).collect();
+    }
+
+    let data: Value = from_str(s).map_err(|e| Error::json("Failed to parse breach names", e))?;
+
+    match data.as_array() {
+        Some(data) => {
+            data.iter()
+                .map(|d| {
+                    let obj = d.as_object()
+                        .ok_or_else(|| {
+                            Error::parse(format!("Failed to convert internal object from \
+                                                  response: {}",
+                                                 snippet(&d.to_string())))
+                        })?;
+                    BreachName::new(get_serde_string(get_or_err("Name", obj)?)?)
+                })
+                .collect()
+        }
+        None => Err(Error::parse(format!("Improperly formatted response: {}", snippet(s)))),
+    }
+}
+
+pub fn breach_from_str(s: &str) -> Result<Breach> {
+    let data: Value = from_str(s).map_err(|e| Error::json("Failed to parse breach", e))?;
+
+    match data.as_object() {
+        Some(obj) => parse_breach(obj),
+        None => Err(Error::parse(format!("Improperly formatted response: {}", snippet(s)))),
+    }
+}
+
+fn parse_paste(obj: &Map<String, Value>) -> Result<Paste> {
+    Ok(Paste {
+        source: get_serde_string(get_or_err("Source", obj)?)?,
+        id: get_serde_string(get_or_err("Id", obj)?)?,
+        title: get_or_err("Title", obj)?.as_str().map(String::from),
+        date: get_or_err("Date", obj)?.as_str().map(String::from),
+        email_count: get_serde_u64(get_or_err("EmailCount", obj)?)?,
+    })
+}
+
+pub fn pastes_from_str(s: &str) -> Result<Vec<Paste>> {
+    let data: Value = from_str(s).map_err(|e| Error::json("Failed to parse pastes", e))?;
+
+    match data.as_array() {
+        Some(data) => {
+            data.iter()
+                .map(|d| d.as_object())
+                .collect::<Option<Vec<_>>>()
+                .map_or(Err(Error::parse(format!("Failed to convert internal object from \
+                                                  response: {}",
+                                                 snippet(s)))),
+                        |o| {
+                            o.into_iter()
+                                .map(parse_paste)
+                                .collect::<Result<Vec<_>>>()
+                        })
+        }
+        None => Err(Error::parse(format!("Improperly formatted response: {}", snippet(s)))),
+    }
+}
+
+pub fn data_classes_from_str(s: &str) -> Result<Vec<DataClass>> {
+    let data: Value = from_str(s).map_err(|e| Error::json("Failed to parse data classes", e))?;
+
+    data.as_array()
+        .map(|d| {
+            d.iter()
+                .map(|dc| get_serde_string(dc).map(|name| DataClass::from_name(&name)))
+                .collect::<Result<Vec<_>>>()
+        })
+        .unwrap_or(Err(Error::parse(format!("Failed to parse DataClass into array of string: {}",
+                                            snippet(s)))))
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UserFindings {
+    pub breaches: Vec<Breach>,
+    pub unknown: Vec<BreachName>,
+}
+
+pub fn join_breaches(users: &BTreeMap<String, Vec<BreachName>>,
+                     catalogue: &Catalogue)
+                     -> BTreeMap<String, UserFindings> {
+    users.iter()
+        .map(|(user, names)| {
+            let mut findings = UserFindings::default();
+            for name in names {
+                match catalogue.get(name.as_ref()) {
+                    Some(breach) => findings.breaches.push(breach.to_breach()),
+                    None => findings.unknown.push(name.clone()),
+                }
+            }
+            (user.clone(), findings)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_single_breach_object() {
+        let breaches = breaches_from_str(r#"{"Name":"Adobe","PwnCount":152445165}"#).unwrap();
+
+        assert_eq!(breaches.len(), 1);
+        assert_eq!(breaches[0].name, "Adobe");
+        assert_eq!(breaches[0].pwn_count, Some(152445165));
+    }
+
+    #[test]
+    fn breaches_are_keyed_by_name() {
+        let mut breaches = breaches_from_str(r#"[{"Name":"Yahoo"},
+                                                 {"Name":"Adobe","PwnCount":1},
+                                                 {"Name":"Adobe","PwnCount":2}]"#)
+            .unwrap();
+        breaches.sort();
+        breaches.dedup();
+
+        let names: Vec<_> = breaches.iter().map(|b| b.name.as_str()).collect();
+        assert_eq!(names, ["Adobe", "Yahoo"]);
+    }
+
+    #[test]
+    fn displays_breach_summary() {
+        let breaches = breaches_from_str(r#"{"Name":"Adobe","Title":"Adobe","Domain":"adobe.com",
+                                             "BreachDate":"2013-10-04","PwnCount":152445165,
+                                             "DataClasses":["Email addresses","Passwords"]}"#)
+            .unwrap();
+
+        assert_eq!(breaches[0].to_string(),
+                   "Adobe (adobe.com) \u{2014} 152,445,165 accounts, breached 2013-10-04: \
+                    Email addresses, Passwords");
+    }
+
+    #[test]
+    fn displays_paste_summary() {
+        let pastes = pastes_from_str(r#"[{"Source":"Pastebin","Id":"8Q0BvKD8","Title":"syslog",
+                                          "Date":"2014-03-04T19:14:54Z","EmailCount":1139}]"#)
+            .unwrap();
+
+        assert_eq!(pastes[0].to_string(),
+                   "Pastebin 8Q0BvKD8 \"syslog\" \u{2014} 1,139 emails, posted 2014-03-04T19:14:54Z");
+    }
+
+    #[test]
+    fn parses_truncated_breaches() {
+        let names = breach_names_from_str(r#"[{"Name":"Adobe"},{"Name":"Gawker"}]"#).unwrap();
+
+        let names: Vec<_> = names.iter().map(BreachName::as_str).collect();
+        assert_eq!(names, ["Adobe", "Gawker"]);
+    }
+
+    #[test]
+    fn truncated_fast_path_matches_the_slow_path() {
+        let names = breach_names_from_str(r#"[{"Name":"Ad\u006fbe","Title":"x"}]"#).unwrap();
+        assert_eq!(names[0].as_str(), "Adobe");
+
+        assert!(breach_names_from_str(r#"[{"Name":"../breaches"}]"#).is_err());
+        let err = breach_names_from_str(r#"[{"Title":"Adobe"}]"#).unwrap_err();
+        assert_eq!(err.to_string(), "Failed to get field: \"Name\"");
+    }
+
+    #[test]
+    fn validates_breach_names() {
+        assert_eq!("Adobe".parse::<BreachName>().unwrap().as_str(), "Adobe");
+        assert!("".parse::<BreachName>().is_err());
+        assert!("..".parse::<BreachName>().is_err());
+        assert!("../breaches".parse::<BreachName>().is_err());
+        assert!("Adobe?domain=x".parse::<BreachName>().is_err());
+    }
+
+    #[test]
+    fn paste_requires_source() {
+        assert!(pastes_from_str(r#"[{"Id":"8Q0BvKD8","Title":null,"Date":null,"EmailCount":1}]"#)
+            .is_err());
+    }
+
+    #[test]
+    fn joins_users_to_catalogue() {
+        let breaches = breaches_from_str(r#"[{"Name":"Adobe","DataClasses":["Passwords"]}]"#)
+            .unwrap();
+        let catalogue = Catalogue::new(&breaches);
+        let mut users = BTreeMap::new();
+        users.insert("alias".to_owned(),
+                     vec!["Adobe".parse().unwrap(), "Unlisted".parse().unwrap()]);
+
+        let findings = join_breaches(&users, &catalogue);
+        assert_eq!(findings["alias"].breaches[0].data_classes,
+                   Some(vec!["Passwords".to_owned()]));
+        assert_eq!(findings["alias"].unknown, vec!["Unlisted".parse().unwrap()]);
+    }
+}
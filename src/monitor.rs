@@ -0,0 +1,232 @@
+use crate::audit::{plan_request, read_keyed, write_keyed, DryRun};
+use crate::clientv2::Clientv2;
+use crate::errors::*;
+use crate::model::Paste;
+use crate::redact::Pseudonymizer;
+
+use serde_json::{Map, Value};
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Default)]
+pub struct PollReport {
+    pub new: BTreeMap<String, Vec<Paste>>,
+    pub failures: Vec<(String, Error)>,
+}
+
+#[derive(Debug)]
+pub struct PasteMonitor<'a> {
+    client: &'a Clientv2<'a>,
+    accounts: Vec<String>,
+    state: PathBuf,
+    // Keyed by the client's pseudonym for each account, as written to the state file.
+    seen: BTreeMap<String, BTreeSet<String>>,
+}
+
+fn paste_key(paste: &Paste) -> String {
+    format!("{}:{}", paste.source, paste.id)
+}
+
+fn load_seen(path: &Path,
+             pseudonymizer: &Pseudonymizer)
+             -> Result<BTreeMap<String, BTreeSet<String>>> {
+    let Some(data) = read_keyed(path, pseudonymizer)? else {
+        return Ok(BTreeMap::new());
+    };
+    let seen = data.get("seen")
+        .and_then(Value::as_object)
+        .ok_or_else(|| Error::parse("Paste monitor state has no \"seen\" object"))?;
+
+    seen.iter()
+        .map(|(account, keys)| {
+            let keys = keys.as_array()
+                .ok_or_else(|| Error::parse(format!("Seen pastes for {:?} are not a list",
+                                                    account)))?
+                .iter()
+                .map(|k| {
+                    k.as_str()
+                        .map(String::from)
+                        .ok_or_else(|| Error::parse("Seen paste key is not a string"))
+                })
+                .collect::<Result<BTreeSet<_>>>()?;
+            Ok((account.clone(), keys))
+        })
+        .collect()
+}
+
+impl<'a> PasteMonitor<'a> {
+    pub fn open<P: Into<PathBuf>>(client: &'a Clientv2<'a>,
+                                  accounts: Vec<String>,
+                                  state: P)
+                                  -> Result<PasteMonitor<'a>> {
+        let state = state.into();
+        let seen = load_seen(&state, client.pseudonymizer())?;
+
+        Ok(PasteMonitor {
+            client,
+            accounts,
+            state,
+            seen,
+        })
+    }
+
+    pub fn accounts(&self) -> &[String] {
+        &self.accounts
+    }
+
+    pub fn seen(&self, account: &str) -> Option<&BTreeSet<String>> {
+        self.seen.get(&self.client.pseudonym(account))
+    }
+
+    pub fn dry_run(&self) -> DryRun {
+        let mut dry_run = DryRun::default();
+
+        for account in &self.accounts {
+            match self.client.validate_account(account) {
+                Ok(()) => {
+                    let request = self.client.get_pastes(account).request();
+                    let index = dry_run.planned.len();
+                    dry_run.planned.push(plan_request(self.client, account, request, index));
+                }
+                Err(e) => dry_run.failures.push((account.clone(), e)),
+            }
+        }
+        dry_run
+    }
+
+    fn save(&self, seen: &BTreeMap<String, BTreeSet<String>>) -> Result<()> {
+        let seen = seen.iter()
+            .map(|(account, keys)| {
+                (account.clone(), Value::Array(keys.iter().cloned().map(Value::String).collect()))
+            })
+            .collect::<Map<_, _>>();
+
+        let mut body = Map::new();
+        body.insert("seen".to_owned(), Value::Object(seen));
+        write_keyed(&self.state, self.client.pseudonymizer(), body)
+    }
+
+    // A failed account doesn't stop the poll: its error is reported, and every paste marked as
+    // seen is in the returned report. Pastes only count as seen once the state is saved, so a
+    // failed save reports them again on the next poll.
+    pub fn poll(&mut self) -> Result<PollReport> {
+        let mut report = PollReport::default();
+        let mut seen = self.seen.clone();
+
+        for account in &self.accounts {
+            let pastes = match self.client.get_pastes(account).send() {
+                Ok(pastes) => pastes,
+                Err(e) => {
+                    report.failures.push((account.clone(), e));
+                    continue;
+                }
+            };
+
+            let keys = seen.entry(self.client.pseudonym(account)).or_default();
+            let fresh: Vec<Paste> = pastes.into_iter()
+                .filter(|p| keys.insert(paste_key(p)))
+                .collect();
+
+            if !fresh.is_empty() {
+                report.new.insert(account.clone(), fresh);
+            }
+        }
+
+        self.save(&seen)?;
+        self.seen = seen;
+        Ok(report)
+    }
+}
+
+#[cfg(all(test, any(feature = "reqwest", feature = "ureq")))]
+mod tests {
+    use super::*;
+    use crate::backend::{BoxError, HttpBackend, HttpResponse};
+    use crate::protocol::Request;
+
+    use std::fs;
+    use std::io::Cursor;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    // Fails the first request for any account containing "flaky".
+    #[derive(Debug, Default)]
+    struct Pastes(AtomicUsize);
+
+    impl HttpBackend for Pastes {
+        fn get(&self, request: &Request) -> std::result::Result<HttpResponse, BoxError> {
+            let flaky = request.url.path().contains("flaky");
+            let failing = flaky && self.0.fetch_add(1, Ordering::SeqCst) == 0;
+            let status = if failing { 503 } else { 200 };
+
+            Ok(HttpResponse {
+                status,
+                content_type: Some("application/json".to_owned()),
+                retry_after: None,
+                content_length: None,
+                debug_headers: vec![],
+                body: Box::new(Cursor::new(r#"[{"Source":"Pastebin","Id":"8Q0BvKD8","Title":null,
+                                                "Date":null,"EmailCount":1139}]"#)),
+            })
+        }
+    }
+
+    #[test]
+    fn reports_each_paste_once() {
+        let path = std::env::temp_dir().join(format!("hibp-pastes-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let client = Clientv2::with_backend("test-rust-client", Arc::new(Pastes::default()));
+        let accounts = vec!["test@example.com".to_owned()];
+
+        let mut monitor = PasteMonitor::open(&client, accounts.clone(), &path).unwrap();
+        assert_eq!(monitor.poll().unwrap().new["test@example.com"].len(), 1);
+        assert!(monitor.poll().unwrap().new.is_empty());
+
+        let mut monitor = PasteMonitor::open(&client, accounts, &path).unwrap();
+        assert!(monitor.poll().unwrap().new.is_empty());
+        assert!(monitor.seen("test@example.com").unwrap().contains("Pastebin:8Q0BvKD8"));
+        assert!(!fs::read_to_string(&path).unwrap().contains("example.com"));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn failed_saves_do_not_mark_pastes_as_seen() {
+        let dir = std::env::temp_dir().join(format!("hibp-missing-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let client = Clientv2::with_backend("test-rust-client", Arc::new(Pastes::default()));
+        let accounts = vec!["test@example.com".to_owned()];
+        let mut monitor = PasteMonitor::open(&client, accounts, dir.join("state.json")).unwrap();
+
+        assert!(monitor.poll().is_err());
+        assert!(monitor.seen("test@example.com").is_none());
+
+        fs::create_dir(&dir).unwrap();
+        assert_eq!(monitor.poll().unwrap().new["test@example.com"].len(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn failed_accounts_do_not_swallow_earlier_pastes() {
+        let path = std::env::temp_dir().join(format!("hibp-flaky-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let client = Clientv2::with_backend("test-rust-client", Arc::new(Pastes::default()));
+        let accounts = vec!["a@example.com".to_owned(), "flaky@example.com".to_owned()];
+        let mut monitor = PasteMonitor::open(&client, accounts, &path).unwrap();
+
+        let first = monitor.poll().unwrap();
+        assert_eq!(first.new.keys().collect::<Vec<_>>(), ["a@example.com"]);
+        assert_eq!(first.failures[0].0, "flaky@example.com");
+
+        let second = monitor.poll().unwrap();
+        assert_eq!(second.new.keys().collect::<Vec<_>>(), ["flaky@example.com"]);
+        assert!(second.failures.is_empty());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,127 @@
+use crate::backend::HttpBackend;
+use crate::clientv2::Clientv2;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+type Configure = dyn Fn(&str, &mut Clientv2<'static>) + Send + Sync;
+
+pub struct ClientPool {
+    user_agent: String,
+    backend: Arc<dyn HttpBackend>,
+    configure: Box<Configure>,
+    clients: Mutex<HashMap<String, Arc<Clientv2<'static>>>>,
+}
+
+impl fmt::Debug for ClientPool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ClientPool")
+            .field("user_agent", &self.user_agent)
+            .field("backend", &self.backend)
+            .field("tenants", &self.tenants())
+            .finish_non_exhaustive()
+    }
+}
+
+impl ClientPool {
+    #[cfg(any(feature = "reqwest", feature = "ureq"))]
+    pub fn new<U: Into<String>>(user_agent: U) -> ClientPool {
+        ClientPool::with_backend(user_agent, crate::clientv2::default_backend())
+    }
+
+    pub fn with_backend<U>(user_agent: U, backend: Arc<dyn HttpBackend>) -> ClientPool
+        where U: Into<String>
+    {
+        ClientPool {
+            user_agent: user_agent.into(),
+            backend,
+            configure: Box::new(|_, _| {}),
+            clients: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn set_configure<F>(&mut self, configure: F) -> &mut Self
+        where F: Fn(&str, &mut Clientv2<'static>) + Send + Sync + 'static
+    {
+        self.configure = Box::new(configure);
+        self
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Arc<Clientv2<'static>>>> {
+        self.clients.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    // The client is configured without holding the lock, so `configure` may use the pool. If two
+    // threads race to create the same tenant, the first one inserted wins.
+    pub fn client(&self, tenant: &str) -> Arc<Clientv2<'static>> {
+        if let Some(client) = self.lock().get(tenant) {
+            return client.clone();
+        }
+
+        let mut client = Clientv2::with_backend(self.user_agent.clone(), self.backend.clone());
+        (self.configure)(tenant, &mut client);
+
+        self.lock().entry(tenant.to_owned()).or_insert_with(|| Arc::new(client)).clone()
+    }
+
+    pub fn remove(&self, tenant: &str) -> Option<Arc<Clientv2<'static>>> {
+        self.lock().remove(tenant)
+    }
+
+    pub fn tenants(&self) -> Vec<String> {
+        let mut tenants: Vec<_> = self.lock().keys().cloned().collect();
+        tenants.sort();
+        tenants
+    }
+}
+
+#[cfg(all(test, any(feature = "reqwest", feature = "ureq")))]
+mod tests {
+    use super::*;
+    use crate::ratelimit::RateLimiter;
+
+    use std::sync::{OnceLock, Weak};
+    use std::time::Duration;
+
+    #[test]
+    fn tenants_get_isolated_clients() {
+        let mut pool = ClientPool::new("test-rust-client");
+        pool.set_configure(|tenant, client| {
+            if tenant == "paid" {
+                client.set_rate_limiter(Arc::new(RateLimiter::new(Duration::from_millis(100))));
+            }
+        });
+
+        let paid = pool.client("paid");
+        let free = pool.client("free");
+
+        assert!(Arc::ptr_eq(&paid, &pool.client("paid")));
+        assert!(paid.rate_limiter().is_some());
+        assert!(free.rate_limiter().is_none());
+        assert!(!std::ptr::eq(paid.quota(), free.quota()));
+        assert_eq!(pool.tenants(), vec!["free", "paid"]);
+
+        assert!(pool.remove("free").is_some());
+        assert_eq!(pool.tenants(), vec!["paid"]);
+    }
+
+    #[test]
+    fn configure_can_use_the_pool() {
+        let handle = Arc::new(OnceLock::<Weak<ClientPool>>::new());
+        let mut pool = ClientPool::new("test-rust-client");
+        let configure_handle = handle.clone();
+        pool.set_configure(move |tenant, _| {
+            let pool = configure_handle.get().and_then(Weak::upgrade).unwrap();
+            assert!(!pool.tenants().contains(&tenant.to_owned()));
+            if tenant == "child" {
+                pool.client("parent");
+            }
+        });
+
+        let pool = Arc::new(pool);
+        handle.set(Arc::downgrade(&pool)).unwrap();
+        pool.client("child");
+        assert_eq!(pool.tenants(), vec!["child", "parent"]);
+    }
+}
@@ -0,0 +1,110 @@
+use crate::errors::*;
+use crate::request::RetryPolicy;
+
+#[cfg(feature = "reqwest")]
+use crate::transport::TransportConfig;
+
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Profile {
+    Interactive,
+    BulkAudit,
+    Ci,
+}
+
+impl Profile {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Profile::Interactive => "interactive",
+            Profile::BulkAudit => "bulk-audit",
+            Profile::Ci => "ci",
+        }
+    }
+
+    pub fn request_interval(&self) -> Duration {
+        match *self {
+            Profile::Interactive | Profile::Ci => Duration::from_millis(1500),
+            // Leave headroom so long runs don't drift into the API's rate limit.
+            Profile::BulkAudit => Duration::from_millis(1700),
+        }
+    }
+
+    pub fn max_in_flight_per_host(&self) -> usize {
+        match *self {
+            Profile::Interactive => 2,
+            Profile::BulkAudit => 4,
+            Profile::Ci => 1,
+        }
+    }
+
+    pub fn workers(&self) -> usize {
+        match *self {
+            Profile::Interactive | Profile::Ci => 1,
+            Profile::BulkAudit => 4,
+        }
+    }
+
+    pub fn connect_timeout(&self) -> Duration {
+        match *self {
+            Profile::Interactive => Duration::from_secs(5),
+            Profile::BulkAudit => Duration::from_secs(30),
+            Profile::Ci => Duration::from_secs(10),
+        }
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        match *self {
+            Profile::Interactive => {
+                RetryPolicy::new(2).set_backoff(Duration::from_millis(250), Duration::from_secs(2))
+            }
+            Profile::BulkAudit => {
+                RetryPolicy::new(6).set_backoff(Duration::from_secs(2), Duration::from_secs(120))
+            }
+            Profile::Ci => {
+                RetryPolicy::new(3).set_backoff(Duration::from_secs(1), Duration::from_secs(10))
+            }
+        }
+    }
+
+    #[cfg(feature = "reqwest")]
+    pub fn transport(&self) -> TransportConfig {
+        let mut transport = TransportConfig::default();
+        transport.set_connect_timeout(self.connect_timeout());
+        transport
+    }
+}
+
+impl FromStr for Profile {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Profile> {
+        match s {
+            "interactive" => Ok(Profile::Interactive),
+            "bulk-audit" => Ok(Profile::BulkAudit),
+            "ci" => Ok(Profile::Ci),
+            _ => Err(Error::UnknownProfile(s.to_owned())),
+        }
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profiles_round_trip_through_their_names() {
+        for profile in [Profile::Interactive, Profile::BulkAudit, Profile::Ci] {
+            assert_eq!(profile.to_string().parse::<Profile>().unwrap(), profile);
+        }
+        assert!("fast".parse::<Profile>().is_err());
+    }
+}
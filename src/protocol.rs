@@ -0,0 +1,467 @@
+use crate::errors::*;
+
+use crate::model::{breach_from_str, breach_names_from_str, breaches_from_str, data_classes_from_str,
+                   pastes_from_str, Breach, BreachName, DataClass, Paste};
+
+use crate::redact::RedactionPolicy;
+
+use percent_encoding::percent_decode_str;
+use url::Url;
+
+use std::time::Duration;
+
+const API_ROOT: &str = "https://haveibeenpwned.com/api/v2/";
+const MAX_ACCOUNT_LEN: usize = 256;
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(24 * 60 * 60);
+
+pub const DEBUG_HEADERS: &[&str] = &["cf-ray", "x-request-id", "request-id"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Endpoint {
+    BreachedAccount,
+    Breaches,
+    Breach,
+    DataClasses,
+    PasteAccount,
+}
+
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub endpoint: Endpoint,
+    pub url: Url,
+    pub headers: Vec<(&'static str, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+    pub debug_headers: Vec<(String, String)>,
+}
+
+impl Endpoint {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Endpoint::BreachedAccount => "breachedaccount",
+            Endpoint::Breaches => "breaches",
+            Endpoint::Breach => "breach",
+            Endpoint::DataClasses => "dataclasses",
+            Endpoint::PasteAccount => "pasteaccount",
+        }
+    }
+
+    pub fn carries_account(&self) -> bool {
+        matches!(*self, Endpoint::BreachedAccount | Endpoint::PasteAccount)
+    }
+}
+
+impl Request {
+    pub fn get(user_agent: &str, endpoint: Endpoint, url: Url) -> Request {
+        Request {
+            endpoint,
+            url,
+            headers: headers(user_agent),
+        }
+    }
+
+    pub fn redacted_url(&self) -> String {
+        self.redact_url(RedactionPolicy::default())
+    }
+
+    // The account or breach name the request is for, decoded from the last path segment.
+    pub fn subject(&self) -> Option<String> {
+        let segment = self.url.path_segments().and_then(|mut s| s.next_back())?;
+        Some(percent_decode_str(segment).decode_utf8_lossy().into_owned())
+    }
+
+    pub fn redact_url(&self, policy: RedactionPolicy) -> String {
+        let mut url = self.url.clone();
+
+        if self.endpoint.carries_account() {
+            let redacted = policy.apply(&self.subject().unwrap_or_default());
+            if let Ok(mut segments) = url.path_segments_mut() {
+                segments.pop().push(&redacted);
+            }
+        }
+        url.to_string()
+    }
+}
+
+pub fn headers(user_agent: &str) -> Vec<(&'static str, String)> {
+    vec![("User-Agent", user_agent.to_owned())]
+}
+
+pub fn validate_account(account: &str) -> Result<()> {
+    let invalid = |reason| Err(Error::InvalidAccount(account.to_owned(), reason));
+
+    if account.trim().is_empty() {
+        invalid("account is empty")
+    } else if account.len() > MAX_ACCOUNT_LEN {
+        invalid("account is too long")
+    } else if account == "." || account == ".." {
+        invalid("account is a relative path")
+    } else if account.chars().any(char::is_control) {
+        invalid("account contains control characters")
+    } else {
+        Ok(())
+    }
+}
+
+fn endpoint(segments: &[&str]) -> Url {
+    let mut url = Url::parse(API_ROOT).expect("API_ROOT is a valid base URL");
+
+    // Segments are percent-encoded, so an account can't reach another path or the query.
+    if let Ok(mut path) = url.path_segments_mut() {
+        path.pop_if_empty().extend(segments);
+    }
+    url
+}
+
+pub fn parse_base_url(base: &str) -> Result<Url> {
+    let invalid = |reason| Error::InvalidBaseUrl(base.to_owned(), reason);
+
+    let mut url = Url::parse(base).map_err(|_| invalid("not a valid URL"))?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(invalid("scheme must be http or https"));
+    }
+    if url.cannot_be_a_base() || url.query().is_some() || url.fragment().is_some() {
+        return Err(invalid("must be a plain base URL without query or fragment"));
+    }
+
+    if !url.path().ends_with('/') {
+        let path = format!("{}/", url.path());
+        url.set_path(&path);
+    }
+    Ok(url)
+}
+
+pub fn rebase(url: &Url, base: &Url) -> Url {
+    match url.as_str().strip_prefix(API_ROOT) {
+        Some(relative) => base.join(relative).unwrap_or_else(|_| url.clone()),
+        None => url.clone(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AccountQuery<'a> {
+    pub domain: Option<&'a str>,
+    pub truncate: bool,
+    pub include_unverified: bool,
+}
+
+pub fn breached_account_url(account: &str, query: AccountQuery) -> Url {
+    let mut url = endpoint(&["breachedaccount", account]);
+
+    if let Some(d) = query.domain {
+        url.query_pairs_mut().append_pair("domain", d);
+    }
+
+    if query.truncate {
+        url.query_pairs_mut().append_pair("truncateResponse", "true");
+    }
+
+    if query.include_unverified {
+        url.query_pairs_mut().append_pair("includeUnverified", "true");
+    }
+    url
+}
+
+pub fn breaches_url(domain: Option<&str>) -> Url {
+    let mut url = endpoint(&["breaches"]);
+
+    if let Some(d) = domain {
+        url.query_pairs_mut().append_pair("domain", d);
+    }
+
+    url
+}
+
+pub fn breach_url(name: &str) -> Url {
+    endpoint(&["breach", name])
+}
+
+pub fn data_classes_url() -> Url {
+    endpoint(&["dataclasses"])
+}
+
+pub fn paste_account_url(account: &str) -> Url {
+    endpoint(&["pasteaccount", account])
+}
+
+fn charset(content_type: Option<&str>) -> Option<String> {
+    content_type?.split(';')
+        .filter_map(|param| param.split_once('='))
+        .find(|(name, _)| name.trim().eq_ignore_ascii_case("charset"))
+        .map(|(_, value)| value.trim().trim_matches('"').to_lowercase())
+}
+
+fn decode_utf16(bytes: &[u8], big_endian: bool, lossy: bool) -> Result<String> {
+    let units: Vec<u16> = bytes.chunks(2)
+        .map(|pair| {
+            let pair = [pair[0], pair.get(1).copied().unwrap_or(0)];
+            if big_endian {
+                u16::from_be_bytes(pair)
+            } else {
+                u16::from_le_bytes(pair)
+            }
+        })
+        .collect();
+
+    if lossy {
+        return Ok(String::from_utf16_lossy(&units));
+    }
+    let valid_units: usize = char::decode_utf16(units.iter().copied())
+        .map_while(|c| c.ok())
+        .map(char::len_utf16)
+        .sum();
+    let truncated = !bytes.len().is_multiple_of(2);
+    match String::from_utf16(&units) {
+        Ok(body) if !truncated => Ok(body),
+        _ => {
+            Err(Error::InvalidEncoding {
+                encoding: if big_endian { "UTF-16BE" } else { "UTF-16LE" },
+                valid_up_to: valid_units * 2,
+                body_snippet: snippet(&String::from_utf16_lossy(&units)),
+            })
+        }
+    }
+}
+
+// Honours byte order marks first, then a Latin-1 charset, and otherwise expects UTF-8. With
+// `lossy`, undecodable bytes become U+FFFD instead of an InvalidEncoding error.
+pub fn decode_body(bytes: Vec<u8>, content_type: Option<&str>, lossy: bool) -> Result<String> {
+    if let Some(rest) = bytes.strip_prefix(b"\xEF\xBB\xBF") {
+        return decode_body(rest.to_vec(), None, lossy);
+    }
+    if let Some(rest) = bytes.strip_prefix(b"\xFF\xFE") {
+        return decode_utf16(rest, false, lossy);
+    }
+    if let Some(rest) = bytes.strip_prefix(b"\xFE\xFF") {
+        return decode_utf16(rest, true, lossy);
+    }
+
+    if let Some("iso-8859-1" | "latin1" | "latin-1") = charset(content_type).as_deref() {
+        return Ok(bytes.iter().map(|&b| char::from(b)).collect());
+    }
+
+    String::from_utf8(bytes).or_else(|e| {
+        let lossy_body = String::from_utf8_lossy(e.as_bytes()).into_owned();
+        if lossy {
+            Ok(lossy_body)
+        } else {
+            Err(Error::InvalidEncoding {
+                encoding: "UTF-8",
+                valid_up_to: e.utf8_error().valid_up_to(),
+                body_snippet: snippet(&lossy_body),
+            })
+        }
+    })
+}
+
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    value.trim().parse::<u64>().ok().map(|secs| Duration::from_secs(secs).min(MAX_RETRY_AFTER))
+}
+
+fn is_challenge(body: &str) -> bool {
+    let body = body.to_lowercase();
+    ["captcha", "challenge", "just a moment", "attention required"]
+        .iter()
+        .any(|marker| body.contains(marker))
+}
+
+pub fn check_content(status: u16, content_type: Option<&str>, body: &str) -> Result<()> {
+    let is_json = content_type.map(|ct| ct.to_lowercase().contains("json"));
+    let looks_like_html = body.trim_start().starts_with('<');
+
+    if body.is_empty() || is_json == Some(true) || (is_json.is_none() && !looks_like_html) {
+        Ok(())
+    } else if is_challenge(body) {
+        Err(Error::ChallengeRequired(status, vec![]))
+    } else {
+        Err(Error::ServiceUnavailable(status, vec![]))
+    }
+}
+
+fn check_status(response: &Response) -> Result<()> {
+    match response.status {
+        200..=299 => Ok(()),
+        status => Err(Error::UnexpectedStatus(status)),
+    }
+}
+
+// The API answers 404 when an account or breach has no results; any other non-2xx status is an
+// error, so an outage can never be mistaken for a clean account.
+fn found(response: &Response) -> Result<bool> {
+    if response.status == 404 {
+        Ok(false)
+    } else {
+        check_status(response).map(|_| true)
+    }
+}
+
+pub fn parse_breaches(response: &Response) -> Result<Vec<Breach>> {
+    if found(response)? {
+        breaches_from_str(&response.body)
+    } else {
+        Ok(vec![])
+    }
+}
+
+pub fn parse_breach(response: &Response) -> Result<Option<Breach>> {
+    if found(response)? {
+        breach_from_str(&response.body).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+pub fn parse_breach_names(response: &Response) -> Result<Vec<BreachName>> {
+    if found(response)? {
+        breach_names_from_str(&response.body)
+    } else {
+        Ok(vec![])
+    }
+}
+
+pub fn parse_data_classes(response: &Response) -> Result<Vec<DataClass>> {
+    check_status(response)?;
+    data_classes_from_str(&response.body)
+}
+
+pub fn parse_pastes(response: &Response) -> Result<Vec<Paste>> {
+    if found(response)? {
+        pastes_from_str(&response.body)
+    } else {
+        Ok(vec![])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_account_url() {
+        let query = AccountQuery {
+            domain: Some("adobe.com"),
+            truncate: true,
+            include_unverified: true,
+        };
+        let url = breached_account_url("test@example.com", query);
+
+        assert_eq!(url.as_str(),
+                   "https://haveibeenpwned.com/api/v2/breachedaccount/test@example.com\
+                    ?domain=adobe.com&truncateResponse=true&includeUnverified=true");
+    }
+
+    #[test]
+    fn redacts_accounts_from_urls() {
+        let url = breached_account_url("test@example.com", AccountQuery::default());
+        let request = Request::get("test-rust-client", Endpoint::BreachedAccount, url);
+
+        assert_eq!(request.redacted_url(),
+                   "https://haveibeenpwned.com/api/v2/breachedaccount/redacted");
+        assert_eq!(request.redact_url(RedactionPolicy::MaskLocalPart),
+                   "https://haveibeenpwned.com/api/v2/breachedaccount/t***@example.com");
+    }
+
+    #[test]
+    fn rejects_malformed_accounts() {
+        assert!(validate_account("test@example.com").is_ok());
+        assert!(validate_account("  ").is_err());
+        assert!(validate_account("test\n@example.com").is_err());
+        assert!(validate_account(&"a".repeat(MAX_ACCOUNT_LEN + 1)).is_err());
+        assert!(validate_account("..").is_err());
+    }
+
+    #[test]
+    fn accounts_cannot_escape_their_path_segment() {
+        let url = breached_account_url("x/../../breaches?domain=a#b", AccountQuery::default());
+
+        assert_eq!(url.as_str(),
+                   "https://haveibeenpwned.com/api/v2/breachedaccount/\
+                    x%2F..%2F..%2Fbreaches%3Fdomain=a%23b");
+        assert_eq!(url.query(), None);
+    }
+
+    #[test]
+    fn detects_html_responses() {
+        let json = Some("application/json; charset=utf-8");
+        let html = Some("text/html; charset=UTF-8");
+
+        assert!(check_content(200, json, "[]").is_ok());
+        assert!(check_content(404, html, "").is_ok());
+
+        match check_content(503, html, "<html><title>Just a moment...</title></html>") {
+            Err(Error::ChallengeRequired(503, _)) => {}
+            r => panic!("unexpected result: {:?}", r),
+        }
+        match check_content(502, None, "<html>Bad gateway</html>") {
+            Err(Error::ServiceUnavailable(502, _)) => {}
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn parses_retry_after_seconds() {
+        assert_eq!(parse_retry_after(" 2 "), Some(Duration::from_secs(2)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), None);
+        assert_eq!(parse_retry_after("18446744073709551615"), Some(MAX_RETRY_AFTER));
+    }
+
+    #[test]
+    fn decodes_bodies_defensively() {
+        let json = Some("application/json; charset=utf-8");
+
+        assert_eq!(decode_body(b"\xEF\xBB\xBF[]".to_vec(), json, false).unwrap(), "[]");
+        assert_eq!(decode_body(b"\xFF\xFE[\0]\0".to_vec(), json, false).unwrap(), "[]");
+        assert!(decode_body(b"\xFF\xFE[\0]".to_vec(), json, false).is_err());
+        assert_eq!(decode_body(b"[\"caf\xE9\"]".to_vec(), Some("text/plain; charset=ISO-8859-1"),
+                               false)
+                       .unwrap(),
+                   "[\"caf\u{e9}\"]");
+
+        match decode_body(b"[\"caf\xE9\"]".to_vec(), json, false) {
+            Err(Error::InvalidEncoding { encoding: "UTF-8", valid_up_to: 5, .. }) => {}
+            r => panic!("unexpected result: {:?}", r),
+        }
+        assert_eq!(decode_body(b"[\"caf\xE9\"]".to_vec(), json, true).unwrap(),
+                   "[\"caf\u{fffd}\"]");
+    }
+
+    #[test]
+    fn missing_breach_is_none() {
+        let response = Response {
+            status: 404,
+            body: String::new(),
+            debug_headers: vec![],
+        };
+
+        assert_eq!(parse_breach(&response).unwrap(), None);
+    }
+
+    #[test]
+    fn only_not_found_means_no_results() {
+        let response = |status| {
+            Response {
+                status,
+                body: String::new(),
+                debug_headers: vec![],
+            }
+        };
+
+        assert!(parse_pastes(&response(404)).unwrap().is_empty());
+        assert!(parse_breaches(&response(404)).unwrap().is_empty());
+        assert!(parse_breach_names(&response(404)).unwrap().is_empty());
+
+        for status in [200, 403, 503] {
+            assert!(parse_breaches(&response(status)).is_err());
+            assert!(parse_breach_names(&response(status)).is_err());
+            assert!(parse_pastes(&response(status)).is_err());
+        }
+        match parse_breach(&response(503)) {
+            Err(Error::UnexpectedStatus(503)) => {}
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use crate::clientv2::Clientv2;
+use crate::errors::Error;
+use crate::model::{Breach, BreachName, Paste};
+
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyValueError};
+use pyo3::prelude::*;
+
+create_exception!(haveibeenpwnd, HibpError, PyException);
+
+fn to_py_err(e: Error) -> PyErr {
+    match e {
+        Error::InvalidAccount(..) | Error::InvalidBreachName(..) => {
+            PyValueError::new_err(e.to_string())
+        }
+        e => HibpError::new_err(e.to_string()),
+    }
+}
+
+#[pyclass(name = "Client", module = "haveibeenpwnd", frozen)]
+struct PyClient(Clientv2<'static>);
+
+#[pymethods]
+impl PyClient {
+    #[new]
+    fn new(user_agent: String) -> PyClient {
+        PyClient(Clientv2::new(user_agent))
+    }
+
+    #[pyo3(signature = (account, domain = None, include_unverified = false))]
+    fn breaches_for_account(&self,
+                            py: Python<'_>,
+                            account: &str,
+                            domain: Option<&str>,
+                            include_unverified: bool)
+                            -> PyResult<Vec<Breach>> {
+        py.detach(|| {
+                let mut request = self.0
+                    .get_breaches_acct(account)
+                    .set_include_unverified(include_unverified);
+                if let Some(d) = domain {
+                    request = request.set_domain(d);
+                }
+                request.send()
+            })
+            .map_err(to_py_err)
+    }
+
+    fn breach_names_for_account(&self, py: Python<'_>, account: &str) -> PyResult<Vec<String>> {
+        py.detach(|| self.0.get_breaches_acct(account).truncated().send())
+            .map(|names| names.iter().map(BreachName::to_string).collect())
+            .map_err(to_py_err)
+    }
+
+    #[pyo3(signature = (domain = None))]
+    fn breaches(&self, py: Python<'_>, domain: Option<&str>) -> PyResult<Vec<Breach>> {
+        py.detach(|| {
+                let mut request = self.0.get_breaches();
+                if let Some(d) = domain {
+                    request = request.set_domain(d);
+                }
+                request.send()
+            })
+            .map_err(to_py_err)
+    }
+
+    fn breach(&self, py: Python<'_>, name: &str) -> PyResult<Option<Breach>> {
+        let name: BreachName = name.parse().map_err(to_py_err)?;
+        py.detach(|| self.0.get_breach(name).send()).map_err(to_py_err)
+    }
+
+    fn data_classes(&self, py: Python<'_>) -> PyResult<Vec<String>> {
+        py.detach(|| self.0.get_data_classes().send())
+            .map(|classes| classes.iter().map(|c| c.as_str().to_owned()).collect())
+            .map_err(to_py_err)
+    }
+
+    fn pastes(&self, py: Python<'_>, account: &str) -> PyResult<Vec<Paste>> {
+        py.detach(|| self.0.get_pastes(account).send()).map_err(to_py_err)
+    }
+}
+
+#[pymodule]
+fn haveibeenpwnd(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyClient>()?;
+    m.add_class::<Breach>()?;
+    m.add_class::<Paste>()?;
+    m.add("HibpError", m.py().get_type::<HibpError>())?;
+    Ok(())
+}
@@ -0,0 +1,277 @@
+use crate::clock::{Clock, SystemClock};
+use crate::errors::*;
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuotaSnapshot {
+    pub requests_this_window: u32,
+    pub rate_limited_this_window: u32,
+    pub total_rate_limited: u64,
+    pub last_retry_after: Option<Duration>,
+    pub remaining: Option<u32>,
+}
+
+#[derive(Debug)]
+struct QuotaState {
+    window_start: Instant,
+    requests: u32,
+    rate_limited: u32,
+    total_rate_limited: u64,
+    last_retry_after: Option<Duration>,
+    blocked_until: Option<Instant>,
+}
+
+#[derive(Debug)]
+pub struct QuotaTracker {
+    window: Duration,
+    limit: Option<u32>,
+    clock: Arc<dyn Clock>,
+    state: Mutex<QuotaState>,
+}
+
+impl QuotaTracker {
+    pub fn new(window: Duration, limit: Option<u32>) -> QuotaTracker {
+        QuotaTracker::with_clock(window, limit, Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(window: Duration, limit: Option<u32>, clock: Arc<dyn Clock>) -> QuotaTracker {
+        QuotaTracker {
+            window,
+            limit,
+            state: Mutex::new(QuotaState {
+                window_start: clock.now(),
+                requests: 0,
+                rate_limited: 0,
+                total_rate_limited: 0,
+                last_retry_after: None,
+                blocked_until: None,
+            }),
+            clock,
+        }
+    }
+
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    pub fn limit(&self) -> Option<u32> {
+        self.limit
+    }
+
+    fn with_state<T, F: FnOnce(&mut QuotaState) -> T>(&self, f: F) -> T {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let now = self.clock.now();
+
+        if now.duration_since(state.window_start) >= self.window {
+            state.window_start = now;
+            state.requests = 0;
+            state.rate_limited = 0;
+        }
+
+        f(&mut state)
+    }
+
+    pub fn record_request(&self) {
+        self.with_state(|state| state.requests = state.requests.saturating_add(1));
+    }
+
+    pub fn record_rate_limited(&self, retry_after: Option<Duration>) {
+        let now = self.clock.now();
+
+        self.with_state(|state| {
+            state.rate_limited = state.rate_limited.saturating_add(1);
+            state.total_rate_limited = state.total_rate_limited.saturating_add(1);
+            state.last_retry_after = retry_after;
+            state.blocked_until = retry_after.and_then(|r| now.checked_add(r));
+        });
+    }
+
+    pub fn snapshot(&self) -> QuotaSnapshot {
+        let limit = self.limit;
+        let now = self.clock.now();
+
+        self.with_state(|state| {
+            let blocked = state.blocked_until.is_some_and(|until| now < until);
+
+            QuotaSnapshot {
+                requests_this_window: state.requests,
+                rate_limited_this_window: state.rate_limited,
+                total_rate_limited: state.total_rate_limited,
+                last_retry_after: state.last_retry_after,
+                remaining: if blocked {
+                    Some(0)
+                } else {
+                    limit.map(|l| l.saturating_sub(state.requests))
+                },
+            }
+        })
+    }
+}
+
+const HOUR: Duration = Duration::from_secs(60 * 60);
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Clone, Copy)]
+struct BudgetWindow {
+    window: Duration,
+    max_requests: u32,
+    window_start: Instant,
+    spent: u32,
+}
+
+#[derive(Debug)]
+pub struct RequestBudget {
+    clock: Arc<dyn Clock>,
+    windows: Mutex<Vec<BudgetWindow>>,
+}
+
+impl RequestBudget {
+    pub fn new() -> RequestBudget {
+        RequestBudget::with_clock(Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(clock: Arc<dyn Clock>) -> RequestBudget {
+        RequestBudget {
+            clock,
+            windows: Mutex::new(vec![]),
+        }
+    }
+
+    pub fn set_limit(&mut self, window: Duration, max_requests: u32) -> &mut Self {
+        let window_start = self.clock.now();
+        let windows = self.windows.get_mut().unwrap_or_else(|e| e.into_inner());
+
+        windows.retain(|w| w.window != window);
+        windows.push(BudgetWindow {
+            window,
+            max_requests,
+            window_start,
+            spent: 0,
+        });
+        self
+    }
+
+    pub fn per_hour(&mut self, max_requests: u32) -> &mut Self {
+        self.set_limit(HOUR, max_requests)
+    }
+
+    pub fn per_day(&mut self, max_requests: u32) -> &mut Self {
+        self.set_limit(DAY, max_requests)
+    }
+
+    // Either every window has room and the request is charged to all of them, or none are charged.
+    pub fn spend(&self) -> Result<()> {
+        let now = self.clock.now();
+        let mut windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+
+        for w in windows.iter_mut() {
+            let elapsed = now.duration_since(w.window_start);
+            if elapsed >= w.window {
+                w.window_start = now;
+                w.spent = 0;
+            } else if w.spent >= w.max_requests {
+                return Err(Error::BudgetExhausted {
+                    max_requests: w.max_requests,
+                    window: w.window,
+                    resets_in: w.window - elapsed,
+                });
+            }
+        }
+
+        for w in windows.iter_mut() {
+            w.spent += 1;
+        }
+        Ok(())
+    }
+
+    pub fn remaining(&self) -> Option<u32> {
+        let now = self.clock.now();
+        let windows = self.windows.lock().unwrap_or_else(|e| e.into_inner());
+
+        windows.iter()
+            .map(|w| if now.duration_since(w.window_start) >= w.window {
+                      w.max_requests
+                  } else {
+                      w.max_requests.saturating_sub(w.spent)
+                  })
+            .min()
+    }
+}
+
+impl Default for RequestBudget {
+    fn default() -> RequestBudget {
+        RequestBudget::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[test]
+    fn tracks_requests_against_limit() {
+        let quota = QuotaTracker::new(Duration::from_secs(60), Some(10));
+        quota.record_request();
+        quota.record_request();
+
+        let snapshot = quota.snapshot();
+        assert_eq!(snapshot.requests_this_window, 2);
+        assert_eq!(snapshot.remaining, Some(8));
+    }
+
+    #[test]
+    fn retry_after_exhausts_budget() {
+        let clock = Arc::new(MockClock::new());
+        let quota = QuotaTracker::with_clock(Duration::from_secs(60), Some(10), clock.clone());
+        quota.record_request();
+        quota.record_rate_limited(Some(Duration::from_secs(30)));
+
+        let snapshot = quota.snapshot();
+        assert_eq!(snapshot.rate_limited_this_window, 1);
+        assert_eq!(snapshot.last_retry_after, Some(Duration::from_secs(30)));
+        assert_eq!(snapshot.remaining, Some(0));
+
+        clock.advance(Duration::from_secs(31));
+        assert_eq!(quota.snapshot().remaining, Some(9));
+    }
+
+    #[test]
+    fn budget_is_a_hard_limit() {
+        let clock = Arc::new(MockClock::new());
+        let mut budget = RequestBudget::with_clock(clock.clone());
+        budget.per_hour(2).per_day(3);
+
+        assert!(budget.spend().is_ok());
+        clock.advance(Duration::from_secs(600));
+        assert!(budget.spend().is_ok());
+        match budget.spend() {
+            Err(Error::BudgetExhausted { max_requests: 2, resets_in, .. }) => {
+                assert_eq!(resets_in, Duration::from_secs(3000))
+            }
+            r => panic!("unexpected result: {:?}", r),
+        }
+
+        clock.advance(Duration::from_secs(3000));
+        assert_eq!(budget.remaining(), Some(1));
+        assert!(budget.spend().is_ok());
+        assert!(matches!(budget.spend(), Err(Error::BudgetExhausted { max_requests: 3, .. })));
+    }
+
+    #[test]
+    fn window_rolls_over() {
+        let clock = Arc::new(MockClock::new());
+        let quota = QuotaTracker::with_clock(Duration::from_secs(60), Some(10), clock.clone());
+        quota.record_request();
+
+        clock.advance(Duration::from_secs(60));
+        let snapshot = quota.snapshot();
+        assert_eq!(snapshot.requests_this_window, 0);
+        assert_eq!(snapshot.remaining, Some(10));
+    }
+}
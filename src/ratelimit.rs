@@ -0,0 +1,265 @@
+use crate::clock::{Clock, SystemClock};
+
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+const MAX_DEFER: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    Low,
+    #[default]
+    Normal,
+    High,
+}
+
+#[derive(Debug)]
+struct LimiterState {
+    next_slot: Instant,
+    waiting: [usize; 3],
+}
+
+impl LimiterState {
+    fn outranked(&self, priority: Priority) -> bool {
+        self.waiting[priority as usize + 1..].iter().any(|&n| n > 0)
+    }
+}
+
+#[derive(Debug)]
+pub struct RateLimiter {
+    interval: Duration,
+    clock: Arc<dyn Clock>,
+    state: Mutex<LimiterState>,
+    wakeup: Condvar,
+}
+
+impl RateLimiter {
+    pub fn new(interval: Duration) -> RateLimiter {
+        RateLimiter::with_clock(interval, Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(interval: Duration, clock: Arc<dyn Clock>) -> RateLimiter {
+        RateLimiter {
+            interval,
+            state: Mutex::new(LimiterState {
+                next_slot: clock.now(),
+                waiting: [0; 3],
+            }),
+            clock,
+            wakeup: Condvar::new(),
+        }
+    }
+
+    pub fn per_minute(requests: u32) -> RateLimiter {
+        let millis = 60000 / requests.max(1) as u64;
+        RateLimiter::new(Duration::from_millis(millis))
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    fn lock(&self) -> MutexGuard<'_, LimiterState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn acquire(&self) {
+        self.acquire_with(Priority::Normal)
+    }
+
+    pub fn acquire_with(&self, priority: Priority) {
+        let mut state = self.lock();
+        state.waiting[priority as usize] += 1;
+
+        loop {
+            let now = self.clock.now();
+
+            if state.outranked(priority) {
+                state = self.wakeup
+                    .wait_timeout(state, self.interval)
+                    .unwrap_or_else(|e| e.into_inner())
+                    .0;
+            } else if state.next_slot <= now {
+                state.next_slot = now + self.interval;
+                state.waiting[priority as usize] -= 1;
+                self.wakeup.notify_all();
+                return;
+            } else {
+                let wait = state.next_slot.duration_since(now);
+                drop(state);
+                self.clock.sleep(wait);
+                state = self.lock();
+            }
+        }
+    }
+
+    pub fn defer(&self, delay: Duration) {
+        let mut state = self.lock();
+        let Some(until) = self.clock.now().checked_add(delay.min(MAX_DEFER)) else {
+            return;
+        };
+
+        if until > state.next_slot {
+            state.next_slot = until;
+        }
+        self.wakeup.notify_all();
+    }
+}
+
+#[derive(Debug)]
+pub struct HostLimiter {
+    max_in_flight: usize,
+    in_flight: Mutex<HashMap<String, usize>>,
+    released: Condvar,
+}
+
+#[derive(Debug)]
+pub struct HostPermit<'a> {
+    limiter: &'a HostLimiter,
+    host: String,
+}
+
+impl HostLimiter {
+    pub fn new(max_in_flight: usize) -> HostLimiter {
+        HostLimiter {
+            max_in_flight: max_in_flight.max(1),
+            in_flight: Mutex::new(HashMap::new()),
+            released: Condvar::new(),
+        }
+    }
+
+    pub fn max_in_flight(&self) -> usize {
+        self.max_in_flight
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, usize>> {
+        self.in_flight.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn in_flight(&self, host: &str) -> usize {
+        self.lock().get(host).cloned().unwrap_or(0)
+    }
+
+    pub fn acquire(&self, host: &str) -> HostPermit<'_> {
+        let mut in_flight = self.lock();
+
+        while in_flight.get(host).is_some_and(|&n| n >= self.max_in_flight) {
+            in_flight = self.released.wait(in_flight).unwrap_or_else(|e| e.into_inner());
+        }
+        *in_flight.entry(host.to_owned()).or_insert(0) += 1;
+
+        HostPermit {
+            limiter: self,
+            host: host.to_owned(),
+        }
+    }
+}
+
+impl Drop for HostPermit<'_> {
+    fn drop(&mut self) {
+        let mut in_flight = self.limiter.lock();
+
+        if let Some(n) = in_flight.get_mut(&self.host) {
+            *n -= 1;
+            if *n == 0 {
+                in_flight.remove(&self.host);
+            }
+        }
+        self.limiter.released.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn spaces_requests_across_clones() {
+        let clock = Arc::new(MockClock::new());
+        let limiter = Arc::new(RateLimiter::with_clock(Duration::from_millis(1500),
+                                                       clock.clone()));
+        let other = limiter.clone();
+
+        limiter.acquire();
+        other.acquire();
+        limiter.acquire();
+
+        assert_eq!(clock.elapsed(), Duration::from_millis(3000));
+    }
+
+    #[test]
+    fn defer_pushes_back_next_slot() {
+        let clock = Arc::new(MockClock::new());
+        let limiter = RateLimiter::with_clock(Duration::from_millis(1), clock.clone());
+
+        limiter.defer(Duration::from_secs(30));
+        limiter.acquire();
+
+        assert_eq!(clock.elapsed(), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn defer_caps_huge_delays() {
+        let clock = Arc::new(MockClock::new());
+        let limiter = RateLimiter::with_clock(Duration::from_millis(1), clock.clone());
+
+        limiter.defer(Duration::MAX);
+        limiter.acquire();
+
+        assert_eq!(clock.elapsed(), MAX_DEFER);
+    }
+
+    #[test]
+    fn high_priority_goes_first() {
+        let limiter = Arc::new(RateLimiter::new(Duration::from_millis(50)));
+        let order = Arc::new(Mutex::new(vec![]));
+        limiter.acquire();
+
+        let low = {
+            let (limiter, order) = (limiter.clone(), order.clone());
+            thread::spawn(move || {
+                limiter.acquire_with(Priority::Low);
+                order.lock().unwrap().push(Priority::Low);
+            })
+        };
+        thread::sleep(Duration::from_millis(10));
+        let high = {
+            let (limiter, order) = (limiter.clone(), order.clone());
+            thread::spawn(move || {
+                limiter.acquire_with(Priority::High);
+                order.lock().unwrap().push(Priority::High);
+            })
+        };
+
+        low.join().unwrap();
+        high.join().unwrap();
+        assert_eq!(*order.lock().unwrap(), vec![Priority::High, Priority::Low]);
+    }
+
+    #[test]
+    fn host_limiter_caps_in_flight_requests() {
+        let limiter = Arc::new(HostLimiter::new(1));
+        let permit = limiter.acquire("haveibeenpwned.com");
+        let _other_host = limiter.acquire("example.com");
+
+        let waiter = {
+            let limiter = limiter.clone();
+            thread::spawn(move || {
+                let _permit = limiter.acquire("haveibeenpwned.com");
+                limiter.in_flight("haveibeenpwned.com")
+            })
+        };
+        thread::sleep(Duration::from_millis(20));
+        assert_eq!(limiter.in_flight("haveibeenpwned.com"), 1);
+
+        drop(permit);
+        assert_eq!(waiter.join().unwrap(), 1);
+        assert_eq!(limiter.in_flight("haveibeenpwned.com"), 0);
+    }
+}
@@ -0,0 +1,154 @@
+use crate::errors::Error;
+
+use hmac::{Hmac, KeyInit, Mac};
+use sha2::{Digest, Sha256};
+
+use std::fmt;
+
+const HASH_PREFIX_BYTES: usize = 8;
+const RANDOM_KEY_BYTES: usize = 32;
+
+#[derive(Clone)]
+pub struct Pseudonymizer {
+    key: Option<Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum RedactionPolicy {
+    None,
+    MaskLocalPart,
+    Hash,
+    #[default]
+    Omit,
+}
+
+fn normalize(account: &str) -> String {
+    account.trim().to_lowercase()
+}
+
+fn hex_prefix(digest: &[u8]) -> String {
+    digest.iter().take(HASH_PREFIX_BYTES).map(|b| format!("{:02x}", b)).collect()
+}
+
+impl Pseudonymizer {
+    pub fn new(key: &[u8]) -> Pseudonymizer {
+        Pseudonymizer { key: Some(key.to_vec()) }
+    }
+
+    pub fn random() -> Pseudonymizer {
+        let mut key = vec![0; RANDOM_KEY_BYTES];
+        getrandom::fill(&mut key).expect("the OS random number generator is available");
+        Pseudonymizer::new(&key)
+    }
+
+    // The same unsalted SHA-256 prefix everywhere, so a dictionary of addresses reverses it. Only
+    // for callers that need IDs to match across unrelated clients.
+    pub fn unsalted() -> Pseudonymizer {
+        Pseudonymizer { key: None }
+    }
+
+    pub fn is_keyed(&self) -> bool {
+        self.key.is_some()
+    }
+
+    pub fn id(&self, account: &str) -> String {
+        match self.key {
+            Some(ref key) => {
+                let mut mac = Hmac::<Sha256>::new_from_slice(key)
+                    .expect("HMAC accepts keys of any length");
+                mac.update(normalize(account).as_bytes());
+                format!("hmac:{}", hex_prefix(&mac.finalize().into_bytes()))
+            }
+            None => RedactionPolicy::Hash.apply(account),
+        }
+    }
+}
+
+impl Default for Pseudonymizer {
+    fn default() -> Pseudonymizer {
+        Pseudonymizer::random()
+    }
+}
+
+impl fmt::Debug for Pseudonymizer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Pseudonymizer").field("keyed", &self.is_keyed()).finish()
+    }
+}
+
+impl RedactionPolicy {
+    pub fn apply(&self, account: &str) -> String {
+        match *self {
+            RedactionPolicy::None => account.to_owned(),
+            RedactionPolicy::MaskLocalPart => {
+                match account.rsplit_once('@') {
+                    Some((local, domain)) => {
+                        let first = local.chars().next().map(String::from).unwrap_or_default();
+                        format!("{}***@{}", first, domain)
+                    }
+                    None => "***".to_owned(),
+                }
+            }
+            RedactionPolicy::Hash => {
+                let digest = Sha256::digest(normalize(account).as_bytes());
+                format!("sha256:{}", hex_prefix(&digest))
+            }
+            RedactionPolicy::Omit => "redacted".to_owned(),
+        }
+    }
+
+    pub fn redact_error(&self, e: Error) -> Error {
+        match e {
+            Error::InvalidAccount(account, reason) => {
+                Error::InvalidAccount(self.apply(&account), reason)
+            }
+            e => e,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_each_policy() {
+        let account = "test@example.com";
+
+        assert_eq!(RedactionPolicy::None.apply(account), account);
+        assert_eq!(RedactionPolicy::MaskLocalPart.apply(account), "t***@example.com");
+        assert_eq!(RedactionPolicy::Omit.apply(account), "redacted");
+
+        let hashed = RedactionPolicy::Hash.apply(account);
+        assert!(hashed.starts_with("sha256:") && !hashed.contains("example"));
+        assert_eq!(hashed, RedactionPolicy::Hash.apply(" Test@Example.com"));
+    }
+
+    #[test]
+    fn redacts_accounts_in_errors() {
+        let e = Error::InvalidAccount("test\n@example.com".to_owned(), "bad");
+
+        assert!(!RedactionPolicy::default().redact_error(e).to_string().contains("example"));
+    }
+
+    #[test]
+    fn pseudonyms_depend_on_the_key() {
+        let a = Pseudonymizer::new(b"client-a");
+        let b = Pseudonymizer::new(b"client-b");
+
+        assert_eq!(a.id("test@example.com"), a.id("Test@Example.com "));
+        assert_ne!(a.id("test@example.com"), b.id("test@example.com"));
+        assert!(a.id("test@example.com").starts_with("hmac:"));
+    }
+
+    #[test]
+    fn pseudonyms_are_salted_unless_opted_out() {
+        let account = "test@example.com";
+        let default = Pseudonymizer::default();
+
+        assert!(default.is_keyed());
+        assert!(default.id(account).starts_with("hmac:"));
+        assert_ne!(default.id(account), Pseudonymizer::default().id(account));
+        assert_eq!(Pseudonymizer::unsalted().id(account), RedactionPolicy::Hash.apply(account));
+    }
+}
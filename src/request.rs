@@ -0,0 +1,239 @@
+use crate::clock::{Clock, SystemClock};
+use crate::errors::*;
+use crate::protocol::Request;
+
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Origin {
+    Network,
+    Cache,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checked<T> {
+    pub value: T,
+    pub checked_at: SystemTime,
+    pub origin: Origin,
+    pub debug_headers: Vec<(String, String)>,
+}
+
+impl<T> Checked<T> {
+    pub fn network(value: T) -> Checked<T> {
+        Checked {
+            value,
+            checked_at: SystemTime::now(),
+            origin: Origin::Network,
+            debug_headers: vec![],
+        }
+    }
+
+    pub fn map<U, F: FnOnce(T) -> U>(self, f: F) -> Checked<U> {
+        Checked {
+            value: f(self.value),
+            checked_at: self.checked_at,
+            origin: self.origin,
+            debug_headers: self.debug_headers,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Retrying<R> {
+    request: R,
+    policy: RetryPolicy,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy::new(3)
+    }
+}
+
+impl RetryPolicy {
+    pub fn new(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: max_attempts.max(1),
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    pub fn set_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    pub fn set_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    pub(crate) fn run<T, F>(&self, mut send: F) -> Result<T>
+        where F: FnMut() -> Result<T>
+    {
+        let mut attempt = 0;
+
+        loop {
+            match send() {
+                Ok(output) => return Ok(output),
+                Err(e) => {
+                    match self.delay(&e, attempt) {
+                        Some(delay) => self.clock.sleep(delay),
+                        None => return Err(e),
+                    }
+                }
+            }
+            attempt += 1;
+        }
+    }
+
+    pub fn delay(&self, error: &Error, attempt: u32) -> Option<Duration> {
+        if attempt + 1 >= self.max_attempts {
+            return None;
+        }
+
+        let backoff = self.initial_backoff
+            .checked_mul(1 << attempt.min(16))
+            .unwrap_or(self.max_backoff)
+            .min(self.max_backoff);
+
+        match *error {
+            // Waiting out a Retry-After beyond max_backoff would block longer than the caller
+            // allowed, so that is left to them.
+            Error::RateLimited(Some(secs), _) => {
+                Some(Duration::from_secs(secs))
+                    .filter(|&wait| wait <= self.max_backoff)
+                    .map(|wait| wait.max(backoff))
+            }
+            Error::RateLimited(None, _) |
+            Error::ServiceUnavailable(..) |
+            Error::Read(_) => Some(backoff),
+            Error::UnexpectedStatus(status) if status >= 500 => Some(backoff),
+            Error::InvalidResponse { ref source, .. } => self.delay(source, attempt),
+            #[cfg(feature = "client")]
+            Error::Transport { .. } => Some(backoff),
+            _ => None,
+        }
+    }
+}
+
+impl<R: HibpRequest + Clone> HibpRequest for Retrying<R> {
+    type Output = R::Output;
+
+    fn request(&self) -> Request {
+        self.request.request()
+    }
+
+    fn send(self) -> Result<R::Output> {
+        self.send_checked().map(|checked| checked.value)
+    }
+
+    fn send_checked(self) -> Result<Checked<R::Output>> {
+        self.policy.run(|| self.request.clone().send_once())
+    }
+}
+
+pub trait HibpRequest {
+    type Output;
+
+    fn request(&self) -> Request;
+
+    fn send(self) -> Result<Self::Output>;
+
+    fn send_checked(self) -> Result<Checked<Self::Output>>
+        where Self: Sized
+    {
+        self.send().map(Checked::network)
+    }
+
+    // A single attempt that skips the client's own retry policy, for callers that retry
+    // themselves.
+    fn send_once(self) -> Result<Checked<Self::Output>>
+        where Self: Sized
+    {
+        self.send_checked()
+    }
+
+    fn with_retry(self, policy: RetryPolicy) -> Retrying<Self>
+        where Self: Sized + Clone
+    {
+        Retrying {
+            request: self,
+            policy,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::protocol::{self, Endpoint};
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[derive(Debug, Clone)]
+    struct Flaky(Arc<AtomicUsize>);
+
+    impl HibpRequest for Flaky {
+        type Output = usize;
+
+        fn request(&self) -> Request {
+            Request::get("test-rust-client", Endpoint::Breaches, protocol::breaches_url(None))
+        }
+
+        fn send(self) -> Result<usize> {
+            match self.0.fetch_add(1, Ordering::SeqCst) {
+                0 => Err(Error::ServiceUnavailable(503, vec![])),
+                1 => Err(Error::RateLimited(Some(5), vec![])),
+                n => Ok(n),
+            }
+        }
+    }
+
+    #[test]
+    fn retries_with_backoff() {
+        let clock = Arc::new(MockClock::new());
+        let policy = RetryPolicy::new(3)
+            .set_backoff(Duration::from_secs(1), Duration::from_secs(60))
+            .set_clock(clock.clone());
+
+        let attempts = Arc::new(AtomicUsize::new(0));
+        assert_eq!(Flaky(attempts.clone()).with_retry(policy.clone()).send().unwrap(), 2);
+        assert_eq!(clock.elapsed(), Duration::from_secs(6));
+
+        attempts.store(0, Ordering::SeqCst);
+        assert!(Flaky(attempts).with_retry(RetryPolicy::new(1)).send().is_err());
+    }
+
+    #[test]
+    fn does_not_retry_permanent_errors() {
+        let policy = RetryPolicy::new(5);
+        let error = Error::InvalidAccount(String::new(), "account is empty");
+
+        assert_eq!(policy.delay(&error, 0), None);
+        assert_eq!(policy.delay(&Error::ServiceUnavailable(502, vec![]), 4), None);
+    }
+
+    #[test]
+    fn long_retry_after_is_not_waited_out() {
+        let policy = RetryPolicy::new(5)
+            .set_backoff(Duration::from_secs(1), Duration::from_secs(2));
+
+        assert_eq!(policy.delay(&Error::RateLimited(Some(2), vec![]), 0),
+                   Some(Duration::from_secs(2)));
+        assert_eq!(policy.delay(&Error::RateLimited(Some(86400), vec![]), 0), None);
+    }
+}
@@ -0,0 +1,264 @@
+use crate::protocol::{Endpoint, Request};
+
+use serde_json::Value;
+
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    String,
+    Number,
+    Bool,
+    Strings,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Field {
+    name: &'static str,
+    kind: Kind,
+    required: bool,
+    nullable: bool,
+}
+
+const fn field(name: &'static str, kind: Kind) -> Field {
+    Field {
+        name,
+        kind,
+        required: false,
+        nullable: true,
+    }
+}
+
+const fn required(name: &'static str, kind: Kind) -> Field {
+    Field {
+        name,
+        kind,
+        required: true,
+        nullable: false,
+    }
+}
+
+const BREACH: &[Field] = &[required("Name", Kind::String),
+                           field("Title", Kind::String),
+                           field("Domain", Kind::String),
+                           field("BreachDate", Kind::String),
+                           field("AddedDate", Kind::String),
+                           field("ModifiedDate", Kind::String),
+                           field("PwnCount", Kind::Number),
+                           field("Description", Kind::String),
+                           field("DataClasses", Kind::Strings),
+                           field("IsVerified", Kind::Bool),
+                           field("IsFabricated", Kind::Bool),
+                           field("IsSensitive", Kind::Bool),
+                           field("IsActive", Kind::Bool),
+                           field("IsRetired", Kind::Bool),
+                           field("IsSpamList", Kind::Bool),
+                           field("LogoType", Kind::String),
+                           field("LogoPath", Kind::String)];
+
+const TRUNCATED_BREACH: &[Field] = &[required("Name", Kind::String)];
+
+const PASTE: &[Field] = &[required("Source", Kind::String),
+                          required("Id", Kind::String),
+                          field("Title", Kind::String),
+                          field("Date", Kind::String),
+                          required("EmailCount", Kind::Number)];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Severity {
+    Error,
+    // Doesn't stop the response from parsing, such as a field the API added.
+    Warning,
+}
+
+// Lenient validation only fails on errors; strict validation fails on warnings too.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Strictness {
+    #[default]
+    Lenient,
+    Strict,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    pub path: String,
+    pub problem: String,
+    pub severity: Severity,
+}
+
+impl Strictness {
+    pub fn rejects(&self, mismatch: &Mismatch) -> bool {
+        match *self {
+            Strictness::Lenient => mismatch.severity == Severity::Error,
+            Strictness::Strict => true,
+        }
+    }
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.problem)
+    }
+}
+
+fn kind_of(value: &Value) -> &'static str {
+    match *value {
+        Value::Null => "null",
+        Value::Bool(_) => "bool",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn matches_kind(value: &Value, kind: Kind) -> bool {
+    match kind {
+        Kind::String => value.is_string(),
+        Kind::Number => value.is_u64(),
+        Kind::Bool => value.is_boolean(),
+        Kind::Strings => value.as_array().is_some_and(|a| a.iter().all(Value::is_string)),
+    }
+}
+
+fn mismatch(out: &mut Vec<Mismatch>, path: &str, problem: String) {
+    out.push(Mismatch {
+        path: path.to_owned(),
+        problem,
+        severity: Severity::Error,
+    });
+}
+
+fn check_object(value: &Value, fields: &[Field], path: &str, out: &mut Vec<Mismatch>) {
+    let obj = match value.as_object() {
+        Some(obj) => obj,
+        None => return mismatch(out, path, format!("expected object, found {}", kind_of(value))),
+    };
+
+    for f in fields {
+        let path = format!("{}.{}", path, f.name);
+        match obj.get(f.name) {
+            None if f.required => mismatch(out, &path, "missing required field".to_owned()),
+            None => {}
+            Some(&Value::Null) if f.nullable => {}
+            Some(v) if matches_kind(v, f.kind) => {}
+            Some(v) => mismatch(out, &path, format!("expected {:?}, found {}", f.kind, kind_of(v))),
+        }
+    }
+
+    for key in obj.keys() {
+        if !fields.iter().any(|f| f.name == key) {
+            out.push(Mismatch {
+                path: format!("{}.{}", path, key),
+                problem: "unexpected field".to_owned(),
+                severity: Severity::Warning,
+            });
+        }
+    }
+}
+
+fn check_array<F>(value: &Value, out: &mut Vec<Mismatch>, mut check: F)
+    where F: FnMut(&Value, &str, &mut Vec<Mismatch>)
+{
+    match value.as_array() {
+        Some(items) => {
+            for (i, item) in items.iter().enumerate() {
+                check(item, &format!("$[{}]", i), out);
+            }
+        }
+        None => mismatch(out, "$", format!("expected array, found {}", kind_of(value))),
+    }
+}
+
+fn is_truncated(request: &Request) -> bool {
+    request.url.query_pairs().any(|(k, v)| k == "truncateResponse" && v == "true")
+}
+
+pub fn validate(request: &Request, body: &str) -> Vec<Mismatch> {
+    let mut out = vec![];
+    if body.is_empty() {
+        return out;
+    }
+
+    let value: Value = match serde_json::from_str(body) {
+        Ok(value) => value,
+        Err(e) => {
+            mismatch(&mut out, "$", format!("not valid JSON: {}", e));
+            return out;
+        }
+    };
+
+    match request.endpoint {
+        Endpoint::BreachedAccount if is_truncated(request) => {
+            check_array(&value, &mut out, |v, p, o| check_object(v, TRUNCATED_BREACH, p, o))
+        }
+        Endpoint::BreachedAccount | Endpoint::Breaches => {
+            check_array(&value, &mut out, |v, p, o| check_object(v, BREACH, p, o))
+        }
+        Endpoint::Breach => check_object(&value, BREACH, "$", &mut out),
+        Endpoint::PasteAccount => {
+            check_array(&value, &mut out, |v, p, o| check_object(v, PASTE, p, o))
+        }
+        Endpoint::DataClasses => {
+            check_array(&value, &mut out, |v, p, o| {
+                if !v.is_string() {
+                    mismatch(o, p, format!("expected string, found {}", kind_of(v)));
+                }
+            })
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{self, AccountQuery};
+
+    #[test]
+    fn reports_field_level_mismatches() {
+        let request = Request::get("test-rust-client",
+                                   Endpoint::Breaches,
+                                   protocol::breaches_url(None));
+        let body = r#"[{"Name":"Adobe","PwnCount":"lots","IsMalware":true},{"Title":null}]"#;
+
+        let mismatches: Vec<String> = validate(&request, body)
+            .iter()
+            .map(|m| m.to_string())
+            .collect();
+        assert_eq!(mismatches,
+                   vec!["$[0].PwnCount: expected Number, found string",
+                        "$[0].IsMalware: unexpected field",
+                        "$[1].Name: missing required field"]);
+    }
+
+    #[test]
+    fn unknown_fields_are_only_rejected_when_strict() {
+        let request = Request::get("test-rust-client",
+                                   Endpoint::Breach,
+                                   protocol::breach_url("Adobe"));
+        let mismatches = validate(&request, r#"{"Name":"Adobe","IsMalware":false}"#);
+
+        assert_eq!(mismatches[0].severity, Severity::Warning);
+        assert!(!Strictness::default().rejects(&mismatches[0]));
+        assert!(Strictness::Strict.rejects(&mismatches[0]));
+
+        let missing = validate(&request, r#"{"Title":"Adobe"}"#);
+        assert!(Strictness::Lenient.rejects(&missing[0]));
+    }
+
+    #[test]
+    fn picks_the_truncated_schema() {
+        let query = AccountQuery {
+            truncate: true,
+            ..AccountQuery::default()
+        };
+        let request = Request::get("test-rust-client",
+                                   Endpoint::BreachedAccount,
+                                   protocol::breached_account_url("test@example.com", query));
+
+        assert!(validate(&request, r#"[{"Name":"Adobe"}]"#).is_empty());
+        assert!(validate(&request, "").is_empty());
+        assert_eq!(validate(&request, r#"[{"Name":"Adobe","Title":"Adobe"}]"#).len(), 1);
+    }
+}
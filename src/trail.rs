@@ -0,0 +1,181 @@
+use crate::errors::*;
+use crate::protocol::{Endpoint, Request};
+use crate::redact::Pseudonymizer;
+
+use serde_json::{Map, Value};
+
+use std::fmt::Debug;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Outcome {
+    Success,
+    NotFound,
+    RateLimited,
+    Failed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEntry {
+    pub timestamp: SystemTime,
+    pub endpoint: Endpoint,
+    pub subject: Option<String>,
+    pub status: Option<u16>,
+    pub outcome: Outcome,
+}
+
+pub trait AuditTrail: Debug + Send + Sync {
+    fn record(&self, entry: &AuditEntry) -> Result<()>;
+}
+
+impl Outcome {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Outcome::Success => "success",
+            Outcome::NotFound => "not_found",
+            Outcome::RateLimited => "rate_limited",
+            Outcome::Failed => "failed",
+        }
+    }
+}
+
+impl AuditEntry {
+    pub fn pseudonymized<T>(request: &Request,
+                            status: Option<u16>,
+                            result: &Result<T>,
+                            pseudonymizer: &Pseudonymizer)
+                            -> AuditEntry {
+        let subject = match request.endpoint {
+            e if e.carries_account() => request.subject().map(|a| pseudonymizer.id(&a)),
+            Endpoint::Breach => request.subject(),
+            _ => None,
+        };
+
+        let outcome = match *result {
+            Ok(_) if status == Some(404) => Outcome::NotFound,
+            Ok(_) => Outcome::Success,
+            Err(Error::RateLimited(..)) => Outcome::RateLimited,
+            Err(_) => Outcome::Failed,
+        };
+
+        AuditEntry {
+            timestamp: SystemTime::now(),
+            endpoint: request.endpoint,
+            subject,
+            status,
+            outcome,
+        }
+    }
+
+    pub fn to_value(&self) -> Value {
+        let secs = self.timestamp.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+
+        let mut obj = Map::new();
+        obj.insert("timestamp".to_owned(), Value::from(secs));
+        obj.insert("endpoint".to_owned(), Value::from(self.endpoint.as_str()));
+        obj.insert("subject".to_owned(), self.subject.clone().map_or(Value::Null, Value::from));
+        obj.insert("status".to_owned(), self.status.map_or(Value::Null, Value::from));
+        obj.insert("outcome".to_owned(), Value::from(self.outcome.as_str()));
+        Value::Object(obj)
+    }
+}
+
+#[derive(Debug)]
+pub struct JsonLinesTrail {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl JsonLinesTrail {
+    pub fn open<P: Into<PathBuf>>(path: P) -> Result<JsonLinesTrail> {
+        let path = path.into();
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| Error::AuditTrail(path.clone(), e))?;
+
+        Ok(JsonLinesTrail {
+            path,
+            file: Mutex::new(file),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AuditTrail for JsonLinesTrail {
+    fn record(&self, entry: &AuditEntry) -> Result<()> {
+        let mut line = entry.to_value().to_string();
+        line.push('\n');
+
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        file.write_all(line.as_bytes())
+            .and_then(|_| file.flush())
+            .map_err(|e| Error::AuditTrail(self.path.clone(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{self, AccountQuery};
+
+    use std::fs;
+
+    #[test]
+    fn entries_hash_accounts() {
+        let url = protocol::breached_account_url("test@example.com", AccountQuery::default());
+        let request = Request::get("test-rust-client", Endpoint::BreachedAccount, url);
+
+        let keyed = Pseudonymizer::new(b"secret");
+        let entry = AuditEntry::pseudonymized(&request, Some(404), &Ok(()), &keyed);
+        assert_eq!(entry.outcome, Outcome::NotFound);
+        assert_eq!(entry.subject, Some(keyed.id("test@example.com")));
+
+        let rate_limited = Err(Error::RateLimited(None, vec![]));
+        let entry = AuditEntry::pseudonymized::<()>(&request, Some(429), &rate_limited, &keyed);
+        assert_eq!(entry.outcome, Outcome::RateLimited);
+    }
+
+    #[test]
+    fn hashes_the_account_before_url_encoding() {
+        let keyed = Pseudonymizer::new(b"secret");
+
+        for account in ["a+b@example.com", "a b%20c#d@example.com"] {
+            let url = protocol::paste_account_url(account);
+            let request = Request::get("test-rust-client", Endpoint::PasteAccount, url);
+            let entry = AuditEntry::pseudonymized(&request, Some(200), &Ok(()), &keyed);
+            assert_eq!(entry.subject, Some(keyed.id(account)));
+        }
+    }
+
+    #[test]
+    fn appends_json_lines() {
+        let path = std::env::temp_dir().join(format!("hibp-trail-{}.jsonl", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let request = Request::get("test-rust-client",
+                                   Endpoint::Breach,
+                                   protocol::breach_url("Adobe"));
+        let salted = Pseudonymizer::default();
+        let entry = AuditEntry::pseudonymized(&request, Some(200), &Ok(()), &salted);
+
+        JsonLinesTrail::open(&path).unwrap().record(&entry).unwrap();
+        JsonLinesTrail::open(&path).unwrap().record(&entry).unwrap();
+
+        let log = fs::read_to_string(&path).unwrap();
+        let lines: Vec<Value> = log.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[1]["subject"], "Adobe");
+        assert_eq!(lines[1]["outcome"], "success");
+
+        fs::remove_file(&path).unwrap();
+    }
+}